@@ -0,0 +1,100 @@
+// Throughput benchmark of the Ants Foraging model with a large colony: it runs the same simulation
+// on one thread and, when built with the `parallel` feature, on several threads, and reports the
+// steps per second of each run.
+//
+// cargo run --release --features parallel --bin benchmark -- [--ants <n>] [--steps <n>] [--threads <n>]
+//     [--width <n>] [--height <n>]
+
+use krabmaga::engine::schedule::Schedule;
+use krabmaga::engine::state::State;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use antsforaging::model::map::Map;
+use antsforaging::model::state::ModelState;
+use antsforaging::{HEIGHT, WIDTH};
+
+const ANTS: u32 = 10000;
+const STEPS: u64 = 200;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let ants = parse_arg(&args, "--ants").unwrap_or(ANTS);
+    let steps = parse_arg(&args, "--steps").unwrap_or(STEPS);
+    let width = parse_arg(&args, "--width").unwrap_or(WIDTH);
+    let height = parse_arg(&args, "--height").unwrap_or(HEIGHT);
+    #[cfg(feature = "parallel")]
+    let threads = parse_arg(&args, "--threads").unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+
+    println!(
+        "{} ants on a {}x{} field, {} steps",
+        ants, width, height, steps
+    );
+    #[cfg(not(feature = "parallel"))]
+    {
+        let sequential = run(Schedule::new(), ants, steps, width, height);
+        report("Sequential", sequential, ants, steps);
+        println!("Build with `--features parallel` to compare with a parallel run");
+    }
+    #[cfg(feature = "parallel")]
+    {
+        let sequential = run(Schedule::with_threads(1), ants, steps, width, height);
+        report("Sequential", sequential, ants, steps);
+        let parallel = run(Schedule::with_threads(threads), ants, steps, width, height);
+        report(
+            &format!("Parallel ({} threads)", threads),
+            parallel,
+            ants,
+            steps,
+        );
+        println!(
+            "Speedup: {:.2}",
+            sequential.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
+
+fn exit_with_error(err: String) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
+
+// Value of `<name> <value>` on the command line parsed as T, if given.
+fn parse_arg<T: FromStr>(args: &[String], name: &str) -> Option<T> {
+    let i = args.iter().position(|arg| arg == name)?;
+    let value = args
+        .get(i + 1)
+        .unwrap_or_else(|| exit_with_error(format!("Missing value for {}", name)));
+    Some(
+        value
+            .parse()
+            .unwrap_or_else(|_| exit_with_error(format!("Invalid value for {}: {}", name, value))),
+    )
+}
+
+// Time spent executing the steps, initialization excluded.
+fn run(mut schedule: Schedule, ants: u32, steps: u64, width: i32, height: i32) -> Duration {
+    let mut state = ModelState::new(Map::ellipses(width, height));
+    state.num_ants = ants;
+    state.init(&mut schedule);
+    let start = Instant::now();
+    for _ in 0..steps {
+        schedule.step(&mut state);
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration, ants: u32, steps: u64) {
+    let seconds = elapsed.as_secs_f64();
+    println!(
+        "{}: {:.3} s, {:.1} steps/s, {:.0} ant steps/s",
+        name,
+        seconds,
+        steps as f64 / seconds,
+        (ants as u64 * steps) as f64 / seconds
+    );
+}
@@ -0,0 +1,74 @@
+// Constants of the model, shared by the simulation and the benchmark
+use crate::model::ant::Task;
+use crate::model::diffusion::Kernel;
+use crate::model::pheromone::{DepositRule, LayerConfig};
+use crate::model::policy::Policy;
+
+// Size of the built-in map, unless given with `--width` and `--height`. Maps loaded from a file
+// have their own size.
+pub const WIDTH: i32 = 200;
+pub const HEIGHT: i32 = 200;
+pub const NUM_AGENT: u32 = 100;
+pub const EVAPORATION: f32 = 0.999;
+// Fraction of the pheromone of a cell spreading to its neighbours at each step, 0 disables diffusion
+pub const DIFFUSION: f32 = 0.;
+pub const DIFFUSION_KERNEL: Kernel = Kernel::Moore;
+pub const STEP: u64 = 1000;
+// Nests, as (x, y). Ants are spread evenly among them.
+pub const NESTS: &[(i32, i32)] = &[(175, 175)];
+// Food sources, as (x, y, amount of food). A source disappears once its food is over.
+pub const FOOD_SOURCES: &[(i32, i32, u32)] = &[(25, 25, 1000)];
+// Food of each source of a map loaded from a file, unless given with `--map_food`
+pub const MAP_FOOD: u32 = 1000;
+// Pheromone value
+pub const HOME_LOW_PHEROMONE: f32 = 0.00000000000001;
+pub const FOOD_LOW_PHEROMONE: f32 = 0.00000000000001;
+// Ants action parameters
+pub const REWARD: f32 = 1.;
+pub const MOMENTUM_PROBABILITY: f64 = 0.8;
+pub const RANDOM_ACTION_PROBABILITY: f64 = 0.1;
+pub const UPDATE_CUTDOWN: f32 = 0.9;
+// Colony dynamics, enabled with `--colony`: ants have energy and age, and the nests spawn new ants
+// with the food delivered to them.
+// Energy of a new ant, restored when it takes food from a source
+pub const ANT_ENERGY: f32 = 1000.;
+// Energy consumed at each step
+pub const ENERGY_CONSUME: f32 = 1.;
+// Age, in steps, at which an ant dies
+pub const MAX_AGE: u64 = 5000;
+// Food a nest spends to spawn a new ant, unless given with `--spawn_cost`
+pub const SPAWN_COST: u32 = 5;
+// Castes of the colony, as (relative size, movement policy): ants are split among them according to
+// their sizes. All the ants use the same policy with `--policy <greedy|aco|softmax>`.
+pub const CASTES: &[(u32, Policy)] = &[(1, Policy::Greedy)];
+// Parameters of the policies given with --policy, unless given with `--alpha`, `--beta` and `--temperature`
+pub const ACO_ALPHA: f64 = 1.;
+pub const ACO_BETA: f64 = 1.;
+pub const SOFTMAX_TEMPERATURE: f64 = 0.05;
+// Steps between two analyses of the trails, unless given with `--trail_every`. 0 disables them.
+pub const TRAIL_EVERY: u64 = 100;
+// Pheromone layers of the field. A new pheromone only needs an entry here.
+pub const PHEROMONES: &[LayerConfig] = &[
+    LayerConfig {
+        name: "home",
+        evaporation: EVAPORATION,
+        cutoff: HOME_LOW_PHEROMONE,
+        deposit: DepositRule::Gradient {
+            cutdown: UPDATE_CUTDOWN,
+        },
+        deposited_by: Some(Task::Searching),
+        followed_by: Some(Task::Returning),
+        color: [0, 255, 0],
+    },
+    LayerConfig {
+        name: "food",
+        evaporation: EVAPORATION,
+        cutoff: FOOD_LOW_PHEROMONE,
+        deposit: DepositRule::Gradient {
+            cutdown: UPDATE_CUTDOWN,
+        },
+        deposited_by: Some(Task::Returning),
+        followed_by: Some(Task::Searching),
+        color: [0, 0, 255],
+    },
+];
@@ -0,0 +1,9 @@
+// The Ants Foraging model, shared by the simulation and the benchmark in src/bin, and its
+// visualization.
+mod constants;
+pub mod model;
+
+#[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
+pub mod visualization;
+
+pub use constants::*;
@@ -1,62 +1,145 @@
 #[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
-use krabmaga::simulate;
+use {
+    antsforaging::model::metrics::Metrics, antsforaging::model::trail::TrailAnalysis,
+    krabmaga::simulate,
+};
 
 // Visualization specific imports
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
 use {
-    crate::model::to_food_grid::ToFoodGrid, crate::model::to_home_grid::ToHomeGrid,
-    crate::visualization::vis_state::VisState, krabmaga::bevy::app::FixedUpdate,
+    antsforaging::model::pheromone::Pheromones, antsforaging::model::trail::TrailAnalysis,
+    antsforaging::visualization::vis_state::VisState, krabmaga::bevy::app::FixedUpdate,
     krabmaga::bevy::prelude::Color, krabmaga::visualization::fields::number_grid_2d::BatchRender,
     krabmaga::visualization::visualization::Visualization,
 };
 
 // Global imports, required in all cases
-use crate::model::state::ModelState;
+use antsforaging::model::diffusion::{Diffusion, Kernel};
+use antsforaging::model::map::Map;
+use antsforaging::model::policy::Policy;
+use antsforaging::model::state::ModelState;
+use antsforaging::*;
 
-pub mod model;
+use std::str::FromStr;
 
-// Constants
-pub const WIDTH: i32 = 200;
-pub const HEIGHT: i32 = 200;
-pub const NUM_AGENT: u32 = 100;
-pub const EVAPORATION: f32 = 0.999;
-pub const STEP: u64 = 1000;
-// Nest coordinate range
-pub const HOME_XMIN: i32 = 175;
-pub const HOME_XMAX: i32 = 175;
-pub const HOME_YMIN: i32 = 175;
-pub const HOME_YMAX: i32 = 175;
-// Food coordinate range
-pub const FOOD_XMIN: i32 = 25;
-pub const FOOD_XMAX: i32 = 25;
-pub const FOOD_YMIN: i32 = 25;
-pub const FOOD_YMAX: i32 = 25;
-// Pheromone value
-pub const HOME_LOW_PHEROMONE: f32 = 0.00000000000001;
-pub const FOOD_LOW_PHEROMONE: f32 = 0.00000000000001;
-// Ants action parameters
-pub const REWARD: f32 = 1.;
-pub const MOMENTUM_PROBABILITY: f64 = 0.8;
-pub const RANDOM_ACTION_PROBABILITY: f64 = 0.1;
-pub const UPDATE_CUTDOWN: f32 = 0.9;
+fn exit_with_error(err: String) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
 
-#[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
-pub mod visualization;
+// Value of `<name> <value>` on the command line, if given.
+fn arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let i = args.iter().position(|arg| arg == name)?;
+    match args.get(i + 1) {
+        Some(value) => Some(value.clone()),
+        None => exit_with_error(format!("Missing value for {}", name)),
+    }
+}
+
+// Value of `<name> <value>` on the command line parsed as T, if given.
+fn parse_arg<T: FromStr>(name: &str) -> Option<T> {
+    arg(name).map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| exit_with_error(format!("Invalid value for {}: {}", name, value)))
+    })
+}
+
+// Diffusion of the pheromones, DIFFUSION and DIFFUSION_KERNEL unless given with
+// `--diffusion <coefficient>` and `--diffusion_kernel <4|8>`.
+fn load_diffusion() -> Diffusion {
+    let coefficient = parse_arg("--diffusion").unwrap_or(DIFFUSION);
+    if !(0. ..=1.).contains(&coefficient) {
+        exit_with_error(format!(
+            "Invalid value for --diffusion: {}, expected a value between 0 and 1",
+            coefficient
+        ));
+    }
+    let kernel = match parse_arg("--diffusion_kernel") {
+        Some(neighbours) => {
+            Kernel::from_neighbours(neighbours).unwrap_or_else(|err| exit_with_error(err))
+        }
+        None => DIFFUSION_KERNEL,
+    };
+    Diffusion::new(coefficient, kernel)
+}
+
+// Castes of the colony, CASTES unless a single policy is given with `--policy`.
+fn load_castes() -> Vec<(u32, Policy)> {
+    let policy = match arg("--policy").as_deref() {
+        None => return CASTES.to_vec(),
+        Some("greedy") => Policy::Greedy,
+        Some("aco") => Policy::Aco {
+            alpha: parse_arg("--alpha").unwrap_or(ACO_ALPHA),
+            beta: parse_arg("--beta").unwrap_or(ACO_BETA),
+        },
+        Some("softmax") => {
+            let temperature = parse_arg("--temperature").unwrap_or(SOFTMAX_TEMPERATURE);
+            if temperature <= 0. {
+                exit_with_error(format!("Invalid value for --temperature: {}", temperature));
+            }
+            Policy::Softmax { temperature }
+        }
+        Some(name) => exit_with_error(format!(
+            "Invalid value for --policy: {}, expected greedy, aco or softmax",
+            name
+        )),
+    };
+    vec![(1, policy)]
+}
+
+// Whether `<name>` is on the command line.
+fn flag(name: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == name)
+}
+
+// The map given with `--map <file>` (PNG or ASCII), whose food sources hold MAP_FOOD units of
+// food unless given with `--map_food`, or the built-in one with two ellipses, WIDTH x HEIGHT
+// unless given with `--width` and `--height`.
+fn load_map() -> Map {
+    match arg("--map") {
+        Some(path) => {
+            let food = parse_arg("--map_food").unwrap_or(MAP_FOOD);
+            if food == 0 {
+                exit_with_error(String::from("Invalid value for --map_food: 0"));
+            }
+            Map::load(path, food).unwrap_or_else(|err| exit_with_error(err))
+        }
+        None => {
+            let width = parse_arg("--width").unwrap_or(WIDTH);
+            let height = parse_arg("--height").unwrap_or(HEIGHT);
+            if width <= 0 || height <= 0 {
+                exit_with_error(format!("Invalid field size: {}x{}", width, height));
+            }
+            Map::ellipses(width, height)
+        }
+    }
+}
 
 // Main used when a visualization feature is applied
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
 fn main() {
-    let state = ModelState::new();
+    let mut state = ModelState::new(load_map());
+    state.num_ants = parse_arg("--ants").unwrap_or(NUM_AGENT);
+    state.colony = flag("--colony");
+    state.spawn_cost = parse_arg("--spawn_cost").unwrap_or(SPAWN_COST);
+    if state.spawn_cost == 0 {
+        exit_with_error(String::from("Invalid value for --spawn_cost: 0"));
+    }
+    state.diffusion = load_diffusion();
+    state.castes = load_castes();
+    state.toroidal = flag("--toroidal");
+    state.trails.every = parse_arg("--trail_every").unwrap_or(TRAIL_EVERY);
+    let (width, height) = (state.width, state.height);
     let mut app = Visualization::default()
         .with_background_color(Color::rgb(255., 255., 255.))
-        .with_simulation_dimensions(WIDTH as f32, HEIGHT as f32)
+        .with_simulation_dimensions(width as f32, height as f32)
         .with_window_dimensions(1280., 720.)
         .with_name("Ants foraging")
         .setup::<VisState, ModelState>(VisState, state);
-    app.add_systems(
-        FixedUpdate,
-        (ToHomeGrid::batch_render, ToFoodGrid::batch_render),
-    );
+    app.add_systems(FixedUpdate, Pheromones::batch_render);
+    app.add_systems(FixedUpdate, TrailAnalysis::batch_render);
     app.run()
 }
 
@@ -66,7 +149,35 @@ fn main() {
 // Main used when only the simulation should run, without any visualization.
 #[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
 fn main() {
-    let state = ModelState::new();
+    let mut state = ModelState::new(load_map());
+    state.num_ants = parse_arg("--ants").unwrap_or(NUM_AGENT);
+    state.colony = flag("--colony");
+    state.spawn_cost = parse_arg("--spawn_cost").unwrap_or(SPAWN_COST);
+    if state.spawn_cost == 0 {
+        exit_with_error(String::from("Invalid value for --spawn_cost: 0"));
+    }
+    state.diffusion = load_diffusion();
+    state.castes = load_castes();
+    state.toroidal = flag("--toroidal");
+    // Foraging metrics of every step with `--output <csv>`, and an early stop once the nests
+    // have received `--target_deliveries <n>` units of food
+    if let Some(output) = arg("--output") {
+        state.metrics = Metrics::create(output).unwrap_or_else(|err| exit_with_error(err));
+    }
+    state.target_deliveries = parse_arg("--target_deliveries");
+    // Trail analysis every `--trail_every <n>` steps, written with `--trail_output <csv>`
+    let trail_every = parse_arg("--trail_every").unwrap_or(TRAIL_EVERY);
+    if let Some(output) = arg("--trail_output") {
+        state.trails =
+            TrailAnalysis::create(output, trail_every).unwrap_or_else(|err| exit_with_error(err));
+    } else {
+        state.trails.every = trail_every;
+    }
 
+    let error = state.error.clone();
     let _ = simulate!(state, STEP, 10);
+    let error = error.lock().unwrap().take();
+    if let Some(err) = error {
+        exit_with_error(err);
+    }
 }
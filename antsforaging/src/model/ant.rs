@@ -6,11 +6,21 @@ use krabmaga::rand;
 use krabmaga::rand::Rng;
 use std::hash::{Hash, Hasher};
 
+use crate::model::neighbourhood::Neighbour;
+use crate::model::policy::Policy;
+use crate::model::sites::{FOOD_ID, NEST_ID};
 use crate::model::state::*;
 use crate::{
-    HEIGHT, MOMENTUM_PROBABILITY, RANDOM_ACTION_PROBABILITY, REWARD, UPDATE_CUTDOWN, WIDTH,
+    ANT_ENERGY, ENERGY_CONSUME, MAX_AGE, MOMENTUM_PROBABILITY, RANDOM_ACTION_PROBABILITY, REWARD,
 };
 
+// Task of an ant: looking for food, or bringing it back to a nest.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Task {
+    Searching,
+    Returning,
+}
+
 // A struct representing an ant, with an id, a location, whether it's holding food or not and the
 // current reward, used to increase the pheromone on the location of the ant if a site is reached.
 #[derive(Copy, Clone)]
@@ -29,6 +39,13 @@ pub struct Ant {
     // This will let the agents spread pheromones in the surrounding areas from point of interests
     // so that other agents will know which path to take to do their job.
     pub reward: f32,
+    // Step in which the current trip began, when the ant left the nest
+    pub trip_start: u64,
+    // Index of the caste of the ant in ModelState::castes, which gives its movement policy
+    pub caste: usize,
+    // Energy left and steps lived, with colony dynamics
+    pub energy: f32,
+    pub age: u64,
 }
 
 impl Ant {
@@ -39,179 +56,152 @@ impl Ant {
             last: None,
             has_food,
             reward,
+            trip_start: 0,
+            caste: 0,
+            energy: ANT_ENERGY,
+            age: 0,
         }
     }
 
-    // Deposit a home pheromone if self is not holding food, else deposit a food pheromone,
-    // so that other agents will take in account the pheromone value when choosing the next step's
-    // direction.
-    pub fn deposit_pheromone(&mut self, state: &ModelState) {
-        let x = self.loc.x;
-        let y = self.loc.y;
+    pub fn as_agent(self) -> Box<dyn Agent> {
+        Box::new(self)
+    }
 
-        // Fetch the value of the correct pheromone on our location, depending whether we're holding
-        // food or not.
-        let mut max = if self.has_food {
-            state.to_food_grid.grid.get_value(&self.loc)
+    // What the ant is doing, which decides the pheromones it deposits and follows.
+    pub fn task(&self) -> Task {
+        if self.has_food {
+            Task::Returning
         } else {
-            state.to_home_grid.grid.get_value(&self.loc)
+            Task::Searching
         }
-        .unwrap_or(0.);
+    }
 
-        // Find the highest pheromone we care about in the surrounding 3x3 area to calculate the value
-        // of the pheromone in our current area. Normally, the maximum pheromone in the 3x3 area is fetched
-        // and it is decreased slightly, then it is assigned to our location.
-        for dx in -1..2 {
-            for dy in -1..2 {
-                let _x = dx + x;
-                let _y = dy + y;
-                if _x < 0 || _y < 0 || _x >= WIDTH || _y >= HEIGHT {
-                    // Do not try to take into account out of bounds grid cells
-                    continue;
-                }
-                // Fetch the pheromone in the cell we're analyzing
-                let pheromone = if self.has_food {
-                    state.to_food_grid.grid.get_value(&Int2D { x: _x, y: _y })
-                } else {
-                    state.to_home_grid.grid.get_value(&Int2D { x: _x, y: _y })
-                }
-                .unwrap_or(0.);
-                // Decrease the value a bit, with diagonal cells of our 3x3 grid considered farther
-                let m = (pheromone * {
-                    if dx * dy != 0 {
-                        Ant::diagonal_cutdown()
-                    } else {
-                        UPDATE_CUTDOWN
-                    }
-                }) + self.reward;
-                if m > max {
-                    max = m;
-                }
-            }
-        }
-        // Set the new value of the pheromone we're considering
-        if self.has_food {
-            state.to_food_grid.grid.set_value_location(max, &self.loc);
-        } else {
-            state.to_home_grid.grid.set_value_location(max, &self.loc);
+    // Deposit the pheromones of our current task on our location (a home pheromone while
+    // searching for food, a food pheromone while bringing it back with the default layers),
+    // so that other agents will take in account the pheromone value when choosing the next
+    // step's direction.
+    pub fn deposit_pheromone(&mut self, state: &ModelState) {
+        for layer in state.pheromones.deposited_by(self.task()) {
+            layer.deposit(&self.loc, self.reward, state.bounds().area(self.loc));
         }
         // We have used our reward, reset it
         self.reward = 0.;
     }
 
-    // Step to the next cell by taking into account pheromones. If no pheromones of the right type
-    // are found in a 3x3 grid centered on us, try to step in the same direction of the last frame
-    // with a probability of MOMENTUM_PROBABILITY. Otherwise, step in a random direction with a
-    // probability of RANDOM_ACTION_PROBABILITY.
-    pub fn act(&mut self, state: &ModelState) {
-        let mut rng = rand::rng();
+    // Next cell of the Greedy policy, by taking into account pheromones. If no pheromones of the
+    // right type are found in a 3x3 grid centered on us, try to step in the same direction of the
+    // last frame with a probability of MOMENTUM_PROBABILITY. Otherwise, step in a random direction
+    // with a probability of RANDOM_ACTION_PROBABILITY.
+    fn greedy_step<R: Rng>(&self, state: &ModelState, rng: &mut R) -> Int2D {
         let mut max = -1.; // An initial, impossible pheromone.
-
-        let x = self.loc.x;
-        let y = self.loc.y;
-
-        let mut max_x = x;
-        let mut max_y = y;
+        let mut next = self.loc;
         let mut count = 2; // How many equal pheromones are there around us? Will be used to choose one randomly
 
-        // Check a 3x3 grid centered on us to get a hint on where to step next through the pheromones around us
-        for dx in -1..2 {
-            for dy in -1..2 {
-                let new_x = dx + x;
-                let new_y = dy + y;
-                let new_int2d = Int2D { x: new_x, y: new_y };
-                // Skip the cell we're considering if we're trying to stay still, if we're trying
-                // to exit the field or of we encounter an obstacle
-                if (dx == 0 && dy == 0)
-                    || new_x < 0
-                    || new_y < 0
-                    || new_x >= WIDTH
-                    || new_y >= HEIGHT
-                    || state.get_obstacle(&new_int2d).is_some()
-                {
-                    continue;
-                }
-
-                let m = if self.has_food {
-                    state.to_home_grid.grid.get_value(&new_int2d)
-                } else {
-                    state.to_food_grid.grid.get_value(&new_int2d)
-                }
-                .unwrap_or(0.);
-                if m > max {
-                    // We found a new maximum, reset the count
-                    count = 2;
-                }
-                // A new maximum is found, or the maximux hasn't changed. In the latter case, we
-                // randomly choose whether to consider the new cell for the next step or not with an
-                // equal chance.
-                if m > max || (m == max && rng.random_bool(1. / count as f64)) {
-                    // Latter expression is to take a random step towards paths with a good pheromone
-                    max = m;
-                    max_x = new_x;
-                    max_y = new_y;
-                }
-                count += 1;
+        // Check a 3x3 grid centered on us to get a hint on where to step next through the pheromones
+        // around us, skipping our cell, the ones outside the field and the obstacles
+        for neighbour in state.free_neighbours(&self.loc) {
+            let m = state.pheromones.scent(self.task(), &neighbour.loc);
+            if m > max {
+                // We found a new maximum, reset the count
+                count = 2;
+            }
+            // A new maximum is found, or the maximux hasn't changed. In the latter case, we
+            // randomly choose whether to consider the new cell for the next step or not with an
+            // equal chance.
+            if m > max || (m == max && rng.random_bool(1. / count as f64)) {
+                // Latter expression is to take a random step towards paths with a good pheromone
+                max = m;
+                next = neighbour.loc;
             }
+            count += 1;
         }
 
         if max == 0. && self.last.is_some() {
             // No tips from pheromones, consider stepping in the same direction
             if let Some(last_loc) = self.last {
                 if rng.random_bool(MOMENTUM_PROBABILITY) {
-                    let xm = x + (x - last_loc.x);
-                    let ym = y + (y - last_loc.y);
+                    let (dx, dy) = state.bounds().step_between(&last_loc, &self.loc);
                     // Don't go outside the field or in an obstacle
-                    if (0..WIDTH).contains(&xm)
-                        && (0..HEIGHT).contains(&ym)
-                        && state.get_obstacle(&Int2D { x: xm, y: ym }).is_none()
-                    {
-                        max_x = xm;
-                        max_y = ym;
+                    if let Some(loc) = state.free_cell(&self.loc, dx, dy) {
+                        next = loc;
                     }
                 }
             }
         } else if rng.random_bool(RANDOM_ACTION_PROBABILITY) {
             // All other ideas have failed, just choose a random direction
-            let xd: i32 = rng.random_range(-1..2);
-            let yd: i32 = rng.random_range(-1..2);
-            let xm = x + xd;
-            let ym = y + yd;
+            let dx: i32 = rng.random_range(-1..2);
+            let dy: i32 = rng.random_range(-1..2);
             // Don't go outside the field, in an obstacle and do not stay still
-            if !(xd == 0 && yd == 0)
-                && (0..WIDTH).contains(&xm)
-                && (0..HEIGHT).contains(&ym)
-                && state.get_obstacle(&Int2D { x: xm, y: ym }).is_none()
-            {
-                max_x = xm;
-                max_y = ym;
+            if !(dx == 0 && dy == 0) {
+                if let Some(loc) = state.free_cell(&self.loc, dx, dy) {
+                    next = loc;
+                }
             }
         }
-        let loc = Int2D { x: max_x, y: max_y };
+        next
+    }
+
+    // Step to one of the free cells around us, chosen at random with the probabilities given by
+    // the policy. Stay still if there is none.
+    fn weighted_step<R: Rng>(&self, policy: Policy, state: &ModelState, rng: &mut R) -> Int2D {
+        let candidates: Vec<(Neighbour, f32)> = state
+            .free_neighbours(&self.loc)
+            .map(|neighbour| {
+                let pheromone = state.pheromones.scent(self.task(), &neighbour.loc);
+                (neighbour, pheromone)
+            })
+            .collect();
+        let heading = self
+            .last
+            .map(|last| state.bounds().step_between(&last, &self.loc));
+        policy.choose(&candidates, heading, rng).unwrap_or(self.loc)
+    }
+
+    // Step to the next cell according to the policy of our caste, then get rewarded if a site is
+    // reached.
+    pub fn act(&mut self, state: &ModelState) {
+        let mut rng = rand::rng();
+        let last = self.loc;
+        let loc = match state.castes[self.caste].1 {
+            Policy::Greedy => self.greedy_step(state, &mut rng),
+            policy => self.weighted_step(policy, state, &mut rng),
+        };
         self.loc = loc;
         state.ants_grid.set_object_location(*self, &loc);
-        self.last = Some(Int2D { x, y });
+        self.last = Some(last);
 
         // Get rewarded if we've reached a site and update our food status
         if let Some(obs) = state.obstacles_grid.get_objects(&self.loc) {
-            match obs.first().unwrap().value {
+            let item = obs.first().unwrap();
+            match item.value {
                 ItemType::Home => {
                     if self.has_food {
+                        state.nests[(item.id - NEST_ID) as usize].deliver();
                         {
                             let mut x = state.food_returned_home.write().unwrap();
                             *x = true;
                         }
+                        // The trip ends with this step, the next one starts right away
+                        state
+                            .trip_lengths
+                            .write()
+                            .unwrap()
+                            .push(state.step + 1 - self.trip_start);
+                        self.trip_start = state.step + 1;
                         self.reward = REWARD;
                         self.has_food = !self.has_food;
                     }
                 }
                 ItemType::Food => {
-                    if !self.has_food {
+                    // The last units of a source may be taken by other ants during this step
+                    if !self.has_food && state.food_sources[(item.id - FOOD_ID) as usize].take() {
                         {
                             let mut x = state.food_source_found.write().unwrap();
                             *x = true;
                             //println!("Found food!");
                         }
+                        // Ants feed at the food source
+                        self.energy = ANT_ENERGY;
                         self.reward = REWARD;
                         self.has_food = !self.has_food;
                     }
@@ -220,16 +210,23 @@ impl Ant {
             }
         }
     }
-
-    fn diagonal_cutdown() -> f32 {
-        UPDATE_CUTDOWN.powf((2_f32).sqrt())
-    }
 }
 
 impl Agent for Ant {
     /// Each ant deposits a pheromone in its current location, then it steps in the next grid cell.
+    /// With colony dynamics, the ant ages and consumes energy first, and it dies when it runs out
+    /// of energy or gets too old.
     fn step(&mut self, state: &mut dyn State) {
         let state = state.as_any().downcast_ref::<ModelState>().unwrap();
+        if state.colony {
+            self.age += 1;
+            self.energy -= ENERGY_CONSUME;
+            if self.energy <= 0. || self.age >= MAX_AGE {
+                // Removed from the schedule and the field at the end of the step
+                state.dead_ants.write().unwrap().push(*self);
+                return;
+            }
+        }
         self.deposit_pheromone(state);
         self.act(state);
     }
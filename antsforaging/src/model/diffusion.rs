@@ -0,0 +1,155 @@
+use crate::model::neighbourhood::Bounds;
+use crate::model::pheromone;
+use krabmaga::engine::fields::sparse_number_grid_2d::SparseNumberGrid2D;
+use krabmaga::engine::location::Int2D;
+use krabmaga::hashbrown::HashMap;
+
+// Cells a pheromone spreads to: the 4 orthogonal neighbours (von Neumann) or all the 8 (Moore).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kernel {
+    VonNeumann,
+    Moore,
+}
+
+impl Kernel {
+    pub fn from_neighbours(neighbours: u32) -> Result<Kernel, String> {
+        match neighbours {
+            4 => Ok(Kernel::VonNeumann),
+            8 => Ok(Kernel::Moore),
+            _ => Err(format!(
+                "Invalid diffusion kernel: {} neighbours, expected 4 or 8",
+                neighbours
+            )),
+        }
+    }
+
+    fn offsets(&self) -> &'static [(i32, i32)] {
+        match self {
+            Kernel::VonNeumann => &[(0, 1), (1, 0), (0, -1), (-1, 0)],
+            Kernel::Moore => &[
+                (-1, -1),
+                (-1, 0),
+                (-1, 1),
+                (0, -1),
+                (0, 1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+            ],
+        }
+    }
+}
+
+// Diffusion of the pheromones: at each step every cell gives a fraction `coefficient` of its
+// pheromone to its neighbours, in equal shares. Obstacles and the edge of the field (unless it is
+// toroidal) do not take pheromone, the shares directed to them stay in the cell, so diffusion never
+// creates or destroys pheromone.
+pub struct Diffusion {
+    pub coefficient: f32,
+    pub kernel: Kernel,
+    bounds: Bounds,
+    // Cells holding an obstacle, column by column. Obstacles never move, so they are looked up once.
+    blocked: Vec<bool>,
+}
+
+// Accumulator of the diffused values. A hash map while few cells hold pheromone, a buffer with
+// a value for each cell once they are many, which avoids hashing most of the field at every step.
+enum Buffer {
+    Sparse(HashMap<Int2D, f32>),
+    Dense(Vec<f32>),
+}
+
+impl Diffusion {
+    pub fn new(coefficient: f32, kernel: Kernel) -> Diffusion {
+        Diffusion {
+            coefficient,
+            kernel,
+            bounds: Bounds {
+                width: 0,
+                height: 0,
+                toroidal: false,
+            },
+            blocked: Vec::new(),
+        }
+    }
+
+    // Set the field the pheromones diffuse on.
+    pub fn set_field(&mut self, bounds: Bounds, obstacles: &[Int2D]) {
+        self.bounds = bounds;
+        self.blocked = vec![false; (bounds.width * bounds.height) as usize];
+        for loc in obstacles {
+            self.blocked[(loc.x * bounds.height + loc.y) as usize] = true;
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.coefficient > 0.
+    }
+
+    // The cell at the given offset from `loc`, if it can take pheromone.
+    fn free_cell(&self, loc: &Int2D, dx: i32, dy: i32) -> Option<Int2D> {
+        self.bounds
+            .offset(loc, dx, dy)
+            .filter(|cell| !self.blocked[(cell.x * self.bounds.height + cell.y) as usize])
+    }
+
+    // Diffuse the values of the grid. Only the cells holding pheromone are visited, and the new
+    // values are written back to the grid, which must be updated afterwards.
+    pub fn apply(&self, grid: &SparseNumberGrid2D<f32>) {
+        let cells = pheromone::cells(grid);
+        if cells.is_empty() {
+            return;
+        }
+
+        let offsets = self.kernel.offsets();
+        let (width, height) = (self.bounds.width, self.bounds.height);
+        let mut buffer = if cells.len() * 4 > (width * height) as usize {
+            Buffer::Dense(vec![0.; (width * height) as usize])
+        } else {
+            Buffer::Sparse(HashMap::with_capacity(cells.len() * (offsets.len() + 1)))
+        };
+        let mut add = |loc: Int2D, amount: f32| match &mut buffer {
+            Buffer::Sparse(values) => *values.entry(loc).or_insert(0.) += amount,
+            Buffer::Dense(values) => values[(loc.x * height + loc.y) as usize] += amount,
+        };
+
+        let share_of = self.coefficient / offsets.len() as f32;
+        for (loc, value) in cells.iter() {
+            let share = value * share_of;
+            let mut kept = value - share * offsets.len() as f32;
+            for (dx, dy) in offsets {
+                match self.free_cell(loc, *dx, *dy) {
+                    Some(cell) => add(cell, share),
+                    None => kept += share,
+                }
+            }
+            // Always written, even when empty, to replace the old value of the cell
+            add(*loc, kept);
+        }
+
+        match buffer {
+            Buffer::Sparse(values) => {
+                for (loc, value) in values {
+                    grid.set_value_location(value, &loc);
+                }
+            }
+            Buffer::Dense(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if *value > 0. {
+                        let loc = Int2D {
+                            x: i as i32 / height,
+                            y: i as i32 % height,
+                        };
+                        grid.set_value_location(*value, &loc);
+                    }
+                }
+                // Cells whose pheromone spread out entirely
+                for (loc, _) in cells.iter() {
+                    if values[(loc.x * height + loc.y) as usize] == 0. {
+                        grid.set_value_location(0., loc);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{FOOD_SOURCES, HEIGHT, NESTS, WIDTH};
+use krabmaga::engine::location::Int2D;
+use std::fs;
+use std::path::Path;
+
+// Layout of the field: its size, the obstacles and the sites. It is either the built-in map with
+// two ellipses between the nest and the food, or a map loaded from a file.
+//
+// ASCII maps have one line for each row of the field, the first line being the top one:
+// `#` is an obstacle, `H` a nest, `F` a food source and any other character an empty cell.
+// PNG maps have one pixel for each cell: black pixels are obstacles, blue pixels nests and
+// red pixels food sources. Food sources of a map file all hold the same amount of food.
+#[derive(Clone)]
+pub struct Map {
+    pub width: i32,
+    pub height: i32,
+    pub obstacles: Vec<Int2D>,
+    pub nests: Vec<Int2D>,
+    pub food_sources: Vec<(Int2D, u32)>,
+}
+
+impl Map {
+    // The original field of the model, with the nests and food sources of NESTS and FOOD_SOURCES,
+    // drawn for a WIDTH x HEIGHT field and stretched to the given size.
+    pub fn ellipses(width: i32, height: i32) -> Map {
+        /* General formula to calculate an ellipsis, used to draw obstacles.
+           x and y define a specific cell
+           horizontal and vertical define the ellipsis location (bottom left: 0,0)
+           size defines the ellipsis' size (smaller value = bigger ellipsis)
+        */
+        let ellipsis = |x: f32, y: f32, horizontal: f32, vertical: f32, size: f32| -> bool {
+            ((x - horizontal) * size + (y - vertical) * size)
+                * ((x - horizontal) * size + (y - vertical) * size)
+                / 36.
+                + ((x - horizontal) * size - (y - vertical) * size)
+                    * ((x - horizontal) * size - (y - vertical) * size)
+                    / 1024.
+                <= 1.
+        };
+        let scale_x = WIDTH as f32 / width as f32;
+        let scale_y = HEIGHT as f32 / height as f32;
+
+        let mut obstacles = Vec::new();
+        for i in 0..width {
+            for j in 0..height {
+                // Coordinates of the cell on the WIDTH x HEIGHT field
+                let x = i as f32 * scale_x;
+                let y = j as f32 * scale_y;
+                if ellipsis(x, y, 100., 145., 0.407) || ellipsis(x, y, 90., 55., 0.407) {
+                    obstacles.push(Int2D { x: i, y: j });
+                }
+            }
+        }
+
+        let scale = |x: i32, y: i32| Int2D {
+            x: (x as f32 / scale_x) as i32,
+            y: (y as f32 / scale_y) as i32,
+        };
+        Map {
+            width,
+            height,
+            obstacles,
+            nests: NESTS.iter().map(|(x, y)| scale(*x, *y)).collect(),
+            food_sources: FOOD_SOURCES
+                .iter()
+                .map(|(x, y, food)| (scale(*x, *y), *food))
+                .collect(),
+        }
+    }
+
+    // Load a map from a PNG image, or from an ASCII file for any other extension. Each food source
+    // holds `food` units of food.
+    pub fn load<P: AsRef<Path>>(path: P, food: u32) -> Result<Map, String> {
+        let path = path.as_ref();
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        let map = if is_png {
+            let image = image::open(path)
+                .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?
+                .to_rgba8();
+            let (width, height) = image.dimensions();
+            let mut map = Map::empty(width as i32, height as i32);
+            for (col, row, pixel) in image.enumerate_pixels() {
+                let loc = Int2D {
+                    x: col as i32,
+                    y: (height - 1 - row) as i32,
+                };
+                let [r, g, b, a] = pixel.0;
+                if a < 128 {
+                    continue;
+                }
+                match (r >= 128, g >= 128, b >= 128) {
+                    (false, false, false) => map.obstacles.push(loc),
+                    (false, false, true) => map.nests.push(loc),
+                    (true, false, false) => map.food_sources.push((loc, food)),
+                    _ => {}
+                }
+            }
+            map
+        } else {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+            Map::from_ascii(&text, food)
+        };
+
+        if map.width == 0 || map.height == 0 {
+            return Err(format!("The map {} is empty", path.display()));
+        }
+        if map.nests.is_empty() {
+            return Err(format!("The map {} has no nest", path.display()));
+        }
+        Ok(map)
+    }
+
+    fn from_ascii(text: &str, food: u32) -> Map {
+        let rows: Vec<&str> = text.trim_end().lines().collect();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let height = rows.len();
+        let mut map = Map::empty(width as i32, height as i32);
+        for (row, line) in rows.iter().enumerate() {
+            for (col, cell) in line.chars().enumerate() {
+                let loc = Int2D {
+                    x: col as i32,
+                    y: (height - 1 - row) as i32,
+                };
+                match cell {
+                    '#' => map.obstacles.push(loc),
+                    'H' => map.nests.push(loc),
+                    'F' => map.food_sources.push((loc, food)),
+                    _ => {}
+                }
+            }
+        }
+        map
+    }
+
+    fn empty(width: i32, height: i32) -> Map {
+        Map {
+            width,
+            height,
+            obstacles: Vec::new(),
+            nests: Vec::new(),
+            food_sources: Vec::new(),
+        }
+    }
+}
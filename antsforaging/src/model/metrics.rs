@@ -0,0 +1,206 @@
+use serde::Serialize;
+use std::fs::{self, File};
+use std::path::Path;
+
+// Foraging metrics of a single step, one row of the output file. The steps of the first discovery
+// and of the first delivery are empty until they happen. The row ends with the deliveries of each
+// nest (see NestDeliveries).
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct StepMetrics {
+    // Repetition of the simulation the step belongs to, starting from 1
+    pub run: u32,
+    pub step: u64,
+    pub first_discovery: Option<u64>,
+    pub first_delivery: Option<u64>,
+    // Food delivered to the nests during this step and since the beginning of the run
+    pub deliveries: u32,
+    pub delivered: u32,
+    // Mean length, in steps, of the round trips completed so far: a trip starts when an ant leaves
+    // the nest and ends when it brings the food back
+    pub mean_trip_length: f64,
+    pub carrying_fraction: f64,
+    // Ants alive at the end of the step, ants spawned and died during it, and food in the stores
+    // of the nests, with colony dynamics
+    pub population: u32,
+    pub births: u32,
+    pub deaths: u32,
+    pub colony_food: u32,
+}
+
+// Same columns, in the same order, of StepMetrics. The file is written without the headers of
+// serde, which cannot be written for the variable number of nests.
+const COLUMNS: &[&str] = &[
+    "run",
+    "step",
+    "first_discovery",
+    "first_delivery",
+    "deliveries",
+    "delivered",
+    "mean_trip_length",
+    "carrying_fraction",
+    "population",
+    "births",
+    "deaths",
+    "colony_food",
+];
+
+// Food delivered to a nest during the step and since the beginning of the run, the columns
+// `nest_<i>_deliveries` and `nest_<i>_delivered` of the i-th nest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct NestDeliveries {
+    pub deliveries: u32,
+    pub delivered: u32,
+}
+
+// Foraging metrics of the current run, written to a CSV file (if any) at the end of every step.
+#[derive(Default)]
+pub struct Metrics {
+    pub run: u32,
+    pub first_discovery: Option<u64>,
+    pub first_delivery: Option<u64>,
+    pub delivered: u32,
+    // Food delivered to each nest until the last step
+    pub nest_delivered: Vec<u32>,
+    pub trips: u64,
+    pub trip_steps: u64,
+    writer: Option<csv::Writer<File>>,
+    header_written: bool,
+}
+
+impl Metrics {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Metrics, String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+        }
+        let writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_path(path)
+            .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+        Ok(Metrics {
+            writer: Some(writer),
+            ..Metrics::default()
+        })
+    }
+
+    // Reset the metrics for a new repetition. The rows of all the repetitions go in the same file.
+    pub fn start_run(&mut self) {
+        self.run += 1;
+        self.first_discovery = None;
+        self.first_delivery = None;
+        self.delivered = 0;
+        self.nest_delivered.clear();
+        self.trips = 0;
+        self.trip_steps = 0;
+    }
+
+    pub fn mean_trip_length(&self) -> f64 {
+        if self.trips == 0 {
+            return 0.;
+        }
+        self.trip_steps as f64 / self.trips as f64
+    }
+
+    // Deliveries of each nest during the step, given the food delivered to them so far, which
+    // becomes the starting point of the next step.
+    pub fn nest_deliveries(&mut self, delivered: Vec<u32>) -> Vec<NestDeliveries> {
+        let deliveries = delivered
+            .iter()
+            .enumerate()
+            .map(|(i, delivered)| NestDeliveries {
+                deliveries: delivered - self.nest_delivered.get(i).copied().unwrap_or(0),
+                delivered: *delivered,
+            })
+            .collect();
+        self.nest_delivered = delivered;
+        deliveries
+    }
+
+    // Write the metrics of a step. The file is closed on the first error, which stops the run.
+    pub fn record(
+        &mut self,
+        metrics: &StepMetrics,
+        nests: &[NestDeliveries],
+    ) -> Result<(), String> {
+        if let Some(writer) = self.writer.as_mut() {
+            let header = if self.header_written {
+                Ok(())
+            } else {
+                let nest_columns = (0..nests.len()).flat_map(|i| {
+                    [
+                        format!("nest_{}_deliveries", i),
+                        format!("nest_{}_delivered", i),
+                    ]
+                });
+                let columns = COLUMNS.iter().map(|column| column.to_string());
+                writer.write_record(columns.chain(nest_columns))
+            };
+            // Flush every step: a run stopped early keeps all the rows written so far
+            let written = header
+                .and_then(|_| writer.serialize((metrics, nests)))
+                .and_then(|_| writer.flush().map_err(csv::Error::from));
+            self.header_written = true;
+            if let Err(e) = written {
+                self.writer = None;
+                return Err(format!("Unable to write the foraging metrics: {}", e));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_match_the_step_metrics() {
+        let metrics = StepMetrics {
+            run: 1,
+            step: 2,
+            first_discovery: None,
+            first_delivery: None,
+            deliveries: 0,
+            delivered: 0,
+            mean_trip_length: 0.,
+            carrying_fraction: 0.,
+            population: 0,
+            births: 0,
+            deaths: 0,
+            colony_food: 0,
+        };
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(metrics).unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(output.lines().next(), Some(COLUMNS.join(",").as_str()));
+    }
+
+    #[test]
+    fn deliveries_are_split_by_nest() {
+        let mut metrics = Metrics::default();
+        assert_eq!(
+            metrics.nest_deliveries(vec![2, 0]),
+            vec![
+                NestDeliveries {
+                    deliveries: 2,
+                    delivered: 2
+                },
+                NestDeliveries::default()
+            ]
+        );
+        assert_eq!(
+            metrics.nest_deliveries(vec![3, 4]),
+            vec![
+                NestDeliveries {
+                    deliveries: 1,
+                    delivered: 3
+                },
+                NestDeliveries {
+                    deliveries: 4,
+                    delivered: 4
+                }
+            ]
+        );
+    }
+}
@@ -1,4 +1,10 @@
 pub mod ant;
+pub mod diffusion;
+pub mod map;
+pub mod metrics;
+pub mod neighbourhood;
+pub mod pheromone;
+pub mod policy;
+pub mod sites;
 pub mod state;
-pub mod to_food_grid;
-pub mod to_home_grid;
+pub mod trail;
@@ -0,0 +1,78 @@
+use krabmaga::engine::location::Int2D;
+
+// Offsets of the 3x3 area centred on a cell, the cell itself included.
+pub const AREA: [(i32, i32); 9] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 0),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+// A cell around another one, with its offset from it.
+#[derive(Clone, Copy)]
+pub struct Neighbour {
+    pub loc: Int2D,
+    pub dx: i32,
+    pub dy: i32,
+}
+
+// Bounds of the field. Cells beyond the edges do not exist, unless the field is toroidal, where
+// they wrap around to the opposite edge.
+#[derive(Clone, Copy, Debug)]
+pub struct Bounds {
+    pub width: i32,
+    pub height: i32,
+    pub toroidal: bool,
+}
+
+impl Bounds {
+    // The cell at (x, y), None if it is outside the field.
+    pub fn cell(&self, x: i32, y: i32) -> Option<Int2D> {
+        if self.toroidal {
+            Some(Int2D {
+                x: x.rem_euclid(self.width),
+                y: y.rem_euclid(self.height),
+            })
+        } else if (0..self.width).contains(&x) && (0..self.height).contains(&y) {
+            Some(Int2D { x, y })
+        } else {
+            None
+        }
+    }
+
+    // The cell at the given offset from `loc`, None if it is outside the field.
+    pub fn offset(&self, loc: &Int2D, dx: i32, dy: i32) -> Option<Int2D> {
+        self.cell(loc.x + dx, loc.y + dy)
+    }
+
+    // Offset of a step between two adjacent cells, also when it crosses an edge of a toroidal field.
+    pub fn step_between(&self, from: &Int2D, to: &Int2D) -> (i32, i32) {
+        let wrap = |d: i32, size: i32| {
+            if self.toroidal && d.abs() > 1 {
+                d - d.signum() * size
+            } else {
+                d
+            }
+        };
+        (
+            wrap(to.x - from.x, self.width),
+            wrap(to.y - from.y, self.height),
+        )
+    }
+
+    // The cells of the 3x3 area centred on `loc` that are inside the field, `loc` included.
+    pub fn area(self, loc: Int2D) -> impl Iterator<Item = Neighbour> {
+        AREA.iter().filter_map(move |(dx, dy)| {
+            self.offset(&loc, *dx, *dy).map(|loc| Neighbour {
+                loc,
+                dx: *dx,
+                dy: *dy,
+            })
+        })
+    }
+}
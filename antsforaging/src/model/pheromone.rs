@@ -0,0 +1,276 @@
+use crate::model::ant::Task;
+use crate::model::diffusion::Diffusion;
+use crate::model::neighbourhood::Neighbour;
+use krabmaga::engine::fields::field::Field;
+use krabmaga::engine::fields::grid_option::GridOption;
+use krabmaga::engine::fields::sparse_number_grid_2d::SparseNumberGrid2D;
+use krabmaga::engine::location::Int2D;
+use krabmaga::hashbrown::HashMap;
+use std::sync::RwLock;
+
+// How an ant changes the pheromone of its cell when it deposits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DepositRule {
+    // The highest pheromone of the 3x3 area around the ant, decreased by `cutdown` (more for the
+    // diagonal cells, which are farther) and increased by the reward of the ant, if higher than
+    // the current one. Ants build a gradient growing towards the site they come from.
+    Gradient { cutdown: f32 },
+    // The current pheromone, increased by `amount` and by the reward of the ant. The deposits of
+    // several ants on the same cell during a step do not add up.
+    Constant { amount: f32 },
+}
+
+// Configuration of a pheromone layer.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LayerConfig {
+    // Name of the pheromone, e.g. "home" or "food"
+    pub name: &'static str,
+    // Factor applied to the pheromone of every cell at each step
+    pub evaporation: f32,
+    // Pheromone below this value evaporates completely
+    pub cutoff: f32,
+    pub deposit: DepositRule,
+    // Ants deposit the pheromone while doing this task, and follow it while doing that one. Ants
+    // following several layers sense the sum of their pheromones.
+    pub deposited_by: Option<Task>,
+    pub followed_by: Option<Task>,
+    // Colour of the layer in the visualization
+    pub color: [u8; 3],
+}
+
+// A pheromone on the field. Higher f32 value means more concentrated pheromone.
+// Ants do not write the grid directly: several ants may deposit on the same cell during a step,
+// possibly at the same time with the `parallel` feature, so each cell keeps the highest deposit
+// and the deposits are written to the grid when it is updated. The highest value does not depend
+// on the order of the deposits, and it matches the rules, which never lower the pheromone.
+pub struct PheromoneLayer {
+    pub config: LayerConfig,
+    pub grid: SparseNumberGrid2D<f32>,
+    deposits: RwLock<HashMap<Int2D, f32>>,
+}
+
+impl PheromoneLayer {
+    pub fn new(config: LayerConfig, width: i32, height: i32) -> PheromoneLayer {
+        PheromoneLayer {
+            config,
+            grid: SparseNumberGrid2D::new(width, height),
+            deposits: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn value(&self, loc: &Int2D) -> f32 {
+        self.grid.get_value(loc).unwrap_or(0.)
+    }
+
+    // Deposit pheromone on `loc` according to the deposit rule of the layer, given the 3x3 area
+    // centred on it.
+    pub fn deposit(&self, loc: &Int2D, reward: f32, area: impl Iterator<Item = Neighbour>) {
+        let value = match self.config.deposit {
+            DepositRule::Gradient { cutdown } => {
+                let diagonal_cutdown = cutdown.powf((2_f32).sqrt());
+                let mut max = self.value(loc);
+                for neighbour in area {
+                    let pheromone = self.value(&neighbour.loc);
+                    let m = pheromone
+                        * if neighbour.dx * neighbour.dy != 0 {
+                            diagonal_cutdown
+                        } else {
+                            cutdown
+                        }
+                        + reward;
+                    if m > max {
+                        max = m;
+                    }
+                }
+                max
+            }
+            DepositRule::Constant { amount } => self.value(loc) + amount + reward,
+        };
+        let mut deposits = self.deposits.write().unwrap();
+        let deposit = deposits.entry(*loc).or_insert(value);
+        *deposit = deposit.max(value);
+    }
+
+    pub fn update(&mut self, diffusion: &Diffusion) {
+        for (loc, value) in self.deposits.get_mut().unwrap().drain() {
+            self.grid.set_value_location(value, &loc);
+        }
+        refresh(&mut self.grid);
+        if diffusion.enabled() {
+            diffusion.apply(&self.grid);
+            refresh(&mut self.grid);
+        }
+        let LayerConfig {
+            evaporation,
+            cutoff,
+            ..
+        } = self.config;
+        evaporate(&mut self.grid, |val| {
+            let new_val = val * evaporation;
+            if new_val < cutoff {
+                0.
+            } else {
+                new_val
+            }
+        })
+    }
+}
+
+// The cells of a grid holding pheromone, with their value.
+#[cfg(not(any(
+    feature = "parallel",
+    feature = "visualization",
+    feature = "visualization_wasm"
+)))]
+pub fn cells(grid: &SparseNumberGrid2D<f32>) -> Vec<(Int2D, f32)> {
+    let cells = std::cell::RefCell::new(Vec::new());
+    grid.iter_values(|loc, value| {
+        if *value > 0. {
+            cells.borrow_mut().push((*loc, *value));
+        }
+    });
+    cells.into_inner()
+}
+
+// The grid of the parallel and visualization features has no iter_values, its read buffer is
+// split in shards.
+#[cfg(any(
+    feature = "parallel",
+    feature = "visualization",
+    feature = "visualization_wasm"
+))]
+pub fn cells(grid: &SparseNumberGrid2D<f32>) -> Vec<(Int2D, f32)> {
+    grid.locs
+        .r_shards
+        .iter()
+        .flat_map(|shard| shard.iter())
+        .filter(|(_, value)| **value > 0.)
+        .map(|(loc, value)| (*loc, *value))
+        .collect()
+}
+
+// When updated, the grid of the parallel feature merges its write buffer into the read one instead
+// of replacing it, and values changed in the read buffer are only written to the write one. These
+// helpers give it the behaviour of the sequential grid, so that both runs compute the same
+// pheromone: the read buffer is replaced by the write one, and evaporated values are read by the
+// ants of the next step.
+#[cfg(not(feature = "parallel"))]
+fn refresh(grid: &mut SparseNumberGrid2D<f32>) {
+    grid.update();
+}
+
+#[cfg(feature = "parallel")]
+fn refresh(grid: &mut SparseNumberGrid2D<f32>) {
+    grid.lazy_update();
+}
+
+#[cfg(not(feature = "parallel"))]
+fn evaporate(grid: &mut SparseNumberGrid2D<f32>, closure: impl Fn(&f32) -> f32) {
+    grid.apply_to_all_values(closure, GridOption::READ);
+}
+
+#[cfg(feature = "parallel")]
+fn evaporate(grid: &mut SparseNumberGrid2D<f32>, closure: impl Fn(&f32) -> f32) {
+    grid.apply_to_all_values(closure, GridOption::READWRITE);
+    grid.lazy_update();
+}
+
+// The pheromone layers of the field, one for each entry of PHEROMONES.
+pub struct Pheromones {
+    pub width: i32,
+    pub height: i32,
+    pub layers: Vec<PheromoneLayer>,
+}
+
+impl Pheromones {
+    pub fn new(configs: &[LayerConfig], width: i32, height: i32) -> Pheromones {
+        Pheromones {
+            width,
+            height,
+            layers: configs
+                .iter()
+                .map(|config| PheromoneLayer::new(*config, width, height))
+                .collect(),
+        }
+    }
+
+    // Layers deposited by the ants doing `task`.
+    pub fn deposited_by(&self, task: Task) -> impl Iterator<Item = &PheromoneLayer> {
+        self.layers
+            .iter()
+            .filter(move |layer| layer.config.deposited_by == Some(task))
+    }
+
+    // Layers followed by the ants doing `task`.
+    pub fn followed_by(&self, task: Task) -> impl Iterator<Item = &PheromoneLayer> {
+        self.layers
+            .iter()
+            .filter(move |layer| layer.config.followed_by == Some(task))
+    }
+
+    // Pheromone sensed on `loc` by the ants doing `task`: the sum of the layers they follow, 0 if
+    // they follow none.
+    pub fn scent(&self, task: Task, loc: &Int2D) -> f32 {
+        self.followed_by(task).map(|layer| layer.value(loc)).sum()
+    }
+
+    pub fn update(&mut self, diffusion: &Diffusion) {
+        for layer in self.layers.iter_mut() {
+            layer.update(diffusion);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::diffusion::Kernel;
+
+    // The deposits of a step are read by the ants of the next one, evaporated, with or without
+    // the `parallel` feature.
+    #[test]
+    fn deposits_are_evaporated_once_updated() {
+        let config = LayerConfig {
+            evaporation: 0.5,
+            cutoff: 0.1,
+            deposit: DepositRule::Constant { amount: 1. },
+            ..crate::PHEROMONES[0]
+        };
+        let mut layer = PheromoneLayer::new(config, 10, 10);
+        let loc = Int2D { x: 3, y: 4 };
+        layer.deposit(&loc, 1., std::iter::empty());
+        layer.deposit(&loc, 3., std::iter::empty());
+        assert_eq!(layer.value(&loc), 0.);
+
+        layer.update(&Diffusion::new(0., Kernel::Moore));
+        assert_eq!(layer.value(&loc), 2.);
+        let cells: Vec<(i32, i32, f32)> = cells(&layer.grid)
+            .iter()
+            .map(|(loc, value)| (loc.x, loc.y, *value))
+            .collect();
+        assert_eq!(cells, vec![(3, 4, 2.)]);
+    }
+
+    // Every layer followed by a task steers its ants, not only the first one.
+    #[test]
+    fn followed_layers_add_up() {
+        let layer = |name, amount| LayerConfig {
+            name,
+            evaporation: 1.,
+            deposit: DepositRule::Constant { amount },
+            deposited_by: Some(Task::Searching),
+            followed_by: Some(Task::Returning),
+            ..crate::PHEROMONES[0]
+        };
+        let mut pheromones = Pheromones::new(&[layer("food", 1.), layer("alarm", 2.)], 10, 10);
+        let loc = Int2D { x: 3, y: 4 };
+        for layer in pheromones.deposited_by(Task::Searching) {
+            layer.deposit(&loc, 0., std::iter::empty());
+        }
+        pheromones.update(&Diffusion::new(0., Kernel::Moore));
+
+        assert_eq!(pheromones.followed_by(Task::Returning).count(), 2);
+        assert_eq!(pheromones.scent(Task::Returning, &loc), 3.);
+        assert_eq!(pheromones.scent(Task::Searching, &loc), 0.);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::model::neighbourhood::Neighbour;
+use krabmaga::engine::location::Int2D;
+use krabmaga::rand::Rng;
+
+// Added to the pheromone in the ACO rule, so that cells without pheromone can still be chosen
+const MIN_PHEROMONE: f64 = 0.000001;
+
+// Rule an ant uses to choose the next cell among the free ones around it, according to the
+// pheromone it follows.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Policy {
+    // The cell with the most pheromone, ties broken at random. Without pheromone around, keep the
+    // direction of the last step with probability MOMENTUM_PROBABILITY, and take a random step with
+    // probability RANDOM_ACTION_PROBABILITY otherwise.
+    Greedy,
+    // Ant Colony Optimization: a cell is chosen with probability proportional to
+    // pheromone^alpha * heuristic^beta, where the heuristic favours keeping the direction of the
+    // last step: (2 + cos(turn)) / 3, from 1 going straight to 1/3 going back.
+    Aco { alpha: f64, beta: f64 },
+    // A cell is chosen with probability proportional to exp(pheromone / temperature): higher
+    // temperatures make the choice more random.
+    Softmax { temperature: f64 },
+}
+
+impl Policy {
+    // Choose a cell among the candidates, given as (cell, pheromone), for an ant whose last step
+    // was `heading`. None without candidates. Not used by the Greedy policy.
+    pub fn choose<R: Rng>(
+        &self,
+        candidates: &[(Neighbour, f32)],
+        heading: Option<(i32, i32)>,
+        rng: &mut R,
+    ) -> Option<Int2D> {
+        if candidates.is_empty() {
+            return None;
+        }
+        let weights: Vec<f64> = match *self {
+            Policy::Greedy => return None,
+            Policy::Aco { alpha, beta } => candidates
+                .iter()
+                .map(|(neighbour, pheromone)| {
+                    let heuristic = match heading {
+                        Some(heading) => {
+                            (2. + cos_turn(heading, (neighbour.dx, neighbour.dy))) / 3.
+                        }
+                        None => 1.,
+                    };
+                    (*pheromone as f64 + MIN_PHEROMONE).powf(alpha) * heuristic.powf(beta)
+                })
+                .collect(),
+            Policy::Softmax { temperature } => {
+                // Shifted by the maximum, the exponentials cannot overflow
+                let max = candidates
+                    .iter()
+                    .map(|(_, pheromone)| *pheromone as f64)
+                    .fold(f64::MIN, f64::max);
+                candidates
+                    .iter()
+                    .map(|(_, pheromone)| ((*pheromone as f64 - max) / temperature).exp())
+                    .collect()
+            }
+        };
+
+        let total: f64 = weights.iter().sum();
+        if total <= 0. || !total.is_finite() {
+            return Some(candidates[rng.random_range(0..candidates.len())].0.loc);
+        }
+        let mut target = rng.random_range(0. ..total);
+        for ((neighbour, _), weight) in candidates.iter().zip(weights.iter()) {
+            if target < *weight {
+                return Some(neighbour.loc);
+            }
+            target -= weight;
+        }
+        // Rounding errors may leave the target just above the last weight
+        candidates.last().map(|(neighbour, _)| neighbour.loc)
+    }
+}
+
+// Cosine of the angle between two steps.
+fn cos_turn(a: (i32, i32), b: (i32, i32)) -> f64 {
+    let dot = (a.0 * b.0 + a.1 * b.1) as f64;
+    let norms = (((a.0 * a.0 + a.1 * a.1) * (b.0 * b.0 + b.1 * b.1)) as f64).sqrt();
+    if norms == 0. {
+        return 0.;
+    }
+    dot / norms
+}
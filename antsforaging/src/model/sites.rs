@@ -0,0 +1,82 @@
+use krabmaga::engine::location::Int2D;
+use std::sync::RwLock;
+
+// Ids of the items placed in obstacles_grid for the sites: the item of the i-th nest has id
+// NEST_ID + i, the one of the i-th food source FOOD_ID + i.
+pub const NEST_ID: u32 = 99999999;
+pub const FOOD_ID: u32 = 888888888;
+
+// A nest of the colony, where ants bring the food back.
+pub struct Nest {
+    pub loc: Int2D,
+    // Food delivered to this nest so far
+    pub delivered: RwLock<u32>,
+    // Food delivered and not spent yet, the resource used to spawn new ants
+    pub store: RwLock<u32>,
+}
+
+impl Nest {
+    pub fn new(loc: Int2D) -> Nest {
+        Nest {
+            loc,
+            delivered: RwLock::new(0),
+            store: RwLock::new(0),
+        }
+    }
+
+    pub fn deliver(&self) {
+        *self.delivered.write().unwrap() += 1;
+        *self.store.write().unwrap() += 1;
+    }
+
+    // Spend food from the store, if enough is left.
+    pub fn spend(&self, amount: u32) -> bool {
+        let mut store = self.store.write().unwrap();
+        if *store < amount {
+            return false;
+        }
+        *store -= amount;
+        true
+    }
+
+    pub fn store(&self) -> u32 {
+        *self.store.read().unwrap()
+    }
+
+    pub fn delivered(&self) -> u32 {
+        *self.delivered.read().unwrap()
+    }
+}
+
+// A food patch with a finite amount of food. Each ant takes one unit of food from it.
+pub struct FoodSource {
+    pub loc: Int2D,
+    // Food left in the patch
+    pub food: RwLock<u32>,
+    // False once the patch has been depleted and removed from the field
+    pub on_field: bool,
+}
+
+impl FoodSource {
+    pub fn new(loc: Int2D, food: u32) -> FoodSource {
+        FoodSource {
+            loc,
+            food: RwLock::new(food),
+            on_field: true,
+        }
+    }
+
+    // Take a unit of food, if any is left.
+    pub fn take(&self) -> bool {
+        let mut food = self.food.write().unwrap();
+        if *food == 0 {
+            return false;
+        }
+        *food -= 1;
+        true
+    }
+
+    pub fn food(&self) -> u32 {
+        *self.food.read().unwrap()
+    }
+}
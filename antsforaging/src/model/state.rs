@@ -1,23 +1,26 @@
 use crate::model::ant::Ant;
-use crate::model::to_food_grid::ToFoodGrid;
-use crate::model::to_home_grid::ToHomeGrid;
-use crate::{
-    FOOD_XMAX, FOOD_XMIN, FOOD_YMAX, FOOD_YMIN, HEIGHT, HOME_XMAX, HOME_XMIN, HOME_YMAX, HOME_YMIN,
-    NUM_AGENT, WIDTH,
-};
+use crate::model::diffusion::Diffusion;
+use crate::model::map::Map;
+use crate::model::metrics::{Metrics, StepMetrics};
+use crate::model::neighbourhood::{Bounds, Neighbour};
+use crate::model::pheromone::Pheromones;
+use crate::model::policy::Policy;
+use crate::model::sites::{FoodSource, Nest, FOOD_ID, NEST_ID};
+use crate::model::trail::{self, TrailAnalysis};
+use crate::{CASTES, DIFFUSION, DIFFUSION_KERNEL, NUM_AGENT, PHEROMONES, SPAWN_COST, TRAIL_EVERY};
 use core::fmt;
 use core::hash::{Hash, Hasher};
+#[cfg(feature = "parallel")]
+use krabmaga::engine::agentimpl::AgentImpl;
 use krabmaga::engine::fields::field::Field;
 use krabmaga::engine::fields::sparse_object_grid_2d::SparseGrid2D;
 use krabmaga::engine::location::Int2D;
 use krabmaga::engine::schedule::Schedule;
 use krabmaga::engine::state::State;
-use krabmaga::rand;
-use krabmaga::rand::Rng;
 use std::any::Any;
-use std::sync::RwLock;
+use std::sync::{Arc, Mutex, RwLock};
 // Objects within the field
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ItemType {
     Food,
     Home,
@@ -66,138 +69,180 @@ impl fmt::Display for Item {
 // The global simulation state. This holds the various grids used for movement, exposing setter methods
 // so that the state itself will worry about ownership rules by mutating its own fields.
 pub struct ModelState {
+    // Size of the field, taken from the map
+    pub width: i32,
+    pub height: i32,
+    // Whether ants and pheromones crossing an edge of the field reappear on the opposite one
+    pub toroidal: bool,
+    pub map: Map,
+    // Ants at the beginning of a run
+    pub num_ants: u32,
+    // Colony dynamics: energy and age of the ants, and new ants spawned at a cost of `spawn_cost`
+    // food from the store of a nest
+    pub colony: bool,
+    pub spawn_cost: u32,
+    // Ants alive, id of the next ant spawned, and ants died during the current step
+    pub population: u32,
+    pub next_id: u32,
+    pub dead_ants: RwLock<Vec<Ant>>,
+    // Ants spawned during the last step, for the visualization
+    pub new_ants: Vec<Ant>,
     pub ants_grid: SparseGrid2D<Ant>,
     pub obstacles_grid: SparseGrid2D<Item>,
-    pub to_food_grid: ToFoodGrid,
-    pub to_home_grid: ToHomeGrid,
+    pub pheromones: Pheromones,
+    pub diffusion: Diffusion,
+    // Castes of the colony, as (relative size, movement policy)
+    pub castes: Vec<(u32, Policy)>,
+    pub nests: Vec<Nest>,
+    pub food_sources: Vec<FoodSource>,
     pub food_source_found: RwLock<bool>,
     pub food_returned_home: RwLock<bool>,
+    // Lengths of the trips completed during the current step, collected by after_step
+    pub trip_lengths: RwLock<Vec<u64>>,
+    pub metrics: Metrics,
+    pub trails: TrailAnalysis,
+    // Stop the run once the nests have received this much food
+    pub target_deliveries: Option<u32>,
+    // Error of an output file that stopped the run, shared with main to report it once the
+    // simulation is over
+    pub error: Arc<Mutex<Option<String>>>,
     pub step: u64,
 }
 
 impl State for ModelState {
     fn reset(&mut self) {
         self.step = 0;
-        self.ants_grid = SparseGrid2D::new(WIDTH, HEIGHT);
-        self.obstacles_grid = SparseGrid2D::new(WIDTH, HEIGHT);
-        self.to_food_grid = ToFoodGrid::new(WIDTH, HEIGHT);
-        self.to_home_grid = ToHomeGrid::new(WIDTH, HEIGHT);
+        self.ants_grid = SparseGrid2D::new(self.width, self.height);
+        self.obstacles_grid = SparseGrid2D::new(self.width, self.height);
+        self.pheromones = Pheromones::new(PHEROMONES, self.width, self.height);
+        self.nests = Vec::new();
+        self.food_sources = Vec::new();
         self.food_source_found = RwLock::new(false);
         self.food_returned_home = RwLock::new(false);
+        self.trip_lengths = RwLock::new(Vec::new());
+        self.dead_ants = RwLock::new(Vec::new());
+        self.new_ants = Vec::new();
     }
 
     fn init(&mut self, schedule: &mut Schedule) {
         self.step = 0;
-        self.ants_grid = SparseGrid2D::new(WIDTH, HEIGHT);
-        self.obstacles_grid = SparseGrid2D::new(WIDTH, HEIGHT);
-        self.to_food_grid = ToFoodGrid::new(WIDTH, HEIGHT);
-        self.to_home_grid = ToHomeGrid::new(WIDTH, HEIGHT);
+        self.ants_grid = SparseGrid2D::new(self.width, self.height);
+        self.pheromones = Pheromones::new(PHEROMONES, self.width, self.height);
+        self.diffusion.set_field(self.bounds(), &self.map.obstacles);
         self.food_source_found = RwLock::new(false);
         self.food_returned_home = RwLock::new(false);
+        self.trip_lengths = RwLock::new(Vec::new());
+        self.dead_ants = RwLock::new(Vec::new());
+        self.new_ants = Vec::new();
+        self.metrics.start_run();
+        self.trails.reset(self.width, self.height);
 
-        let mut rng = rand::rng();
-
-        // Food generation
-        let x: i32 = if FOOD_XMIN == FOOD_XMAX {
-            FOOD_XMIN
-        } else {
-            rng.random_range(FOOD_XMIN..FOOD_XMAX)
-        };
-        let y: i32 = if FOOD_YMIN == FOOD_YMAX {
-            FOOD_YMIN
-        } else {
-            rng.random_range(FOOD_YMIN..FOOD_YMAX)
-        };
-        let food_location = Int2D { x, y };
-
-        self.obstacles_grid.set_object_location(
-            Item {
-                id: 888888888,
-                value: ItemType::Food,
-            },
-            &food_location,
-        );
-
-        // Nest generation
-        let x: i32 = if HOME_XMIN == HOME_XMAX {
-            HOME_XMIN
-        } else {
-            rng.random_range(HOME_XMIN..HOME_XMAX)
-        };
-        let y: i32 = if HOME_YMIN == HOME_YMAX {
-            HOME_YMIN
-        } else {
-            rng.random_range(HOME_YMIN..HOME_YMAX)
-        };
-        let nest_location = Int2D { x, y };
-        self.obstacles_grid.set_object_location(
-            Item {
-                id: 99999999,
-                value: ItemType::Home,
-            },
-            &nest_location,
-        );
-
-        // Obastacles generation
-        /* General formula to calculate an ellipsis, used to draw obstacles.
-           x and y define a specific cell
-           horizontal and vertical define the ellipsis location (bottom left: 0,0)
-           size defines the ellipsis' size (smaller value = bigger ellipsis)
-        */
-        let ellipsis = |x: f32, y: f32, horizontal: f32, vertical: f32, size: f32| -> bool {
-            ((x - horizontal) * size + (y - vertical) * size)
-                * ((x - horizontal) * size + (y - vertical) * size)
-                / 36.
-                + ((x - horizontal) * size - (y - vertical) * size)
-                    * ((x - horizontal) * size - (y - vertical) * size)
-                    / 1024.
-                <= 1.
-        };
-
-        let mut obstacle_id = 0;
-        for i in 0..WIDTH {
-            for j in 0..HEIGHT {
-                // Good obstacle placement for 500x500 simulations
-                // if ellipsis(i as f32, j as f32, 300., 345., 0.407)
-                //    || ellipsis(i as f32, j as f32, 190., 155., 0.407)
-                if ellipsis(i as f32, j as f32, 100., 145., 0.407)
-                    || ellipsis(i as f32, j as f32, 90., 55., 0.407)
-                {
-                    let obstacle_location = Int2D { x: i, y: j };
-                    self.obstacles_grid.set_object_location(
-                        Item {
-                            id: obstacle_id,
-                            value: ItemType::Obstacle,
-                        },
-                        &obstacle_location,
-                    );
-                }
-                obstacle_id += 1;
-            }
-        }
+        // Food, nests and obstacles generation
+        self.food_sources = self
+            .map
+            .food_sources
+            .iter()
+            .map(|(food_location, food)| FoodSource::new(*food_location, *food))
+            .collect();
+        self.nests = self.map.nests.iter().map(|loc| Nest::new(*loc)).collect();
+        self.place_items();
 
         // Ants generation
-        for ant_id in 0..NUM_AGENT {
-            let x = (HOME_XMAX + HOME_XMIN) / 2;
-            let y = (HOME_YMAX + HOME_YMIN) / 2;
-            let ant_loc = Int2D { x, y };
+        for ant_id in 0..self.num_ants {
+            let ant_loc = self.nests[ant_id as usize % self.nests.len()].loc;
             // Generate the ant with an initial reward of 1, so that it starts spreading home pheromones
             // around the nest, the initial spawn point.
-            let ant = Ant::new(ant_id, ant_loc, false, 1.);
+            let mut ant = Ant::new(ant_id, ant_loc, false, 1.);
+            ant.caste = self.caste_of(ant_id);
             self.ants_grid.set_object_location(ant, &ant_loc);
             schedule.schedule_repeating(Box::new(ant), 0., 0);
         }
-
-        self.obstacles_grid.update();
+        self.population = self.num_ants;
+        self.next_id = self.num_ants;
     }
 
     fn update(&mut self, step: u64) {
         self.ants_grid.lazy_update();
-        self.to_food_grid.update();
-        self.to_home_grid.update();
+        self.remove_depleted_food();
+        self.pheromones.update(&self.diffusion);
         self.step = step;
     }
 
+    fn after_step(&mut self, schedule: &mut Schedule) {
+        let step = schedule_step(schedule);
+        let mut births = 0;
+        let mut deaths = 0;
+        if self.colony {
+            deaths = self.remove_dead_ants(schedule);
+            births = self.spawn_ants(schedule);
+        }
+        if self.metrics.first_discovery.is_none() && *self.food_source_found.read().unwrap() {
+            self.metrics.first_discovery = Some(step);
+        }
+        if self.metrics.first_delivery.is_none() && *self.food_returned_home.read().unwrap() {
+            self.metrics.first_delivery = Some(step);
+        }
+        for length in self.trip_lengths.write().unwrap().drain(..) {
+            self.metrics.trips += 1;
+            self.metrics.trip_steps += length;
+        }
+
+        let mut ants = 0;
+        let mut carrying = 0;
+        for agent in schedule.get_all_events() {
+            if let Some(ant) = agent.downcast_ref::<Ant>() {
+                ants += 1;
+                if ant.has_food {
+                    carrying += 1;
+                }
+            }
+        }
+
+        let delivered = self.delivered_food();
+        let metrics = StepMetrics {
+            run: self.metrics.run,
+            step,
+            first_discovery: self.metrics.first_discovery,
+            first_delivery: self.metrics.first_delivery,
+            deliveries: delivered - self.metrics.delivered,
+            delivered,
+            mean_trip_length: self.metrics.mean_trip_length(),
+            carrying_fraction: if ants == 0 {
+                0.
+            } else {
+                carrying as f64 / ants as f64
+            },
+            population: self.population,
+            births,
+            deaths,
+            colony_food: self.nests.iter().map(|nest| nest.store()).sum(),
+        };
+        self.metrics.delivered = delivered;
+        let nests = self
+            .metrics
+            .nest_deliveries(self.nests.iter().map(|nest| nest.delivered()).collect());
+        if let Err(err) = self.metrics.record(&metrics, &nests) {
+            self.fail(err);
+        }
+
+        if self.trails.is_due(step) {
+            let (records, trails, shortest_paths) = trail::analyse(self, self.metrics.run, step);
+            self.trails.trails = trails.into_iter().collect();
+            self.trails.shortest_paths = shortest_paths.into_iter().collect();
+            if let Err(err) = self.trails.record(&records) {
+                self.fail(err);
+            }
+        }
+    }
+
+    // The run stops at the delivery target, or when its output cannot be written
+    fn end_condition(&mut self, _schedule: &mut Schedule) -> bool {
+        self.error.lock().unwrap().is_some()
+            || self
+                .target_deliveries
+                .is_some_and(|target| self.delivered_food() >= target)
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -216,18 +261,169 @@ impl State for ModelState {
 }
 
 impl ModelState {
-    pub(crate) fn new() -> ModelState {
+    pub fn new(map: Map) -> ModelState {
+        let (width, height) = (map.width, map.height);
         ModelState {
-            ants_grid: SparseGrid2D::new(WIDTH, HEIGHT),
-            obstacles_grid: SparseGrid2D::new(WIDTH, HEIGHT),
-            to_food_grid: ToFoodGrid::new(WIDTH, HEIGHT),
-            to_home_grid: ToHomeGrid::new(WIDTH, HEIGHT),
+            width,
+            height,
+            toroidal: false,
+            map,
+            num_ants: NUM_AGENT,
+            colony: false,
+            spawn_cost: SPAWN_COST,
+            population: 0,
+            next_id: 0,
+            dead_ants: RwLock::new(Vec::new()),
+            new_ants: Vec::new(),
+            ants_grid: SparseGrid2D::new(width, height),
+            obstacles_grid: SparseGrid2D::new(width, height),
+            pheromones: Pheromones::new(PHEROMONES, width, height),
+            diffusion: Diffusion::new(DIFFUSION, DIFFUSION_KERNEL),
+            castes: CASTES.to_vec(),
+            nests: Vec::new(),
+            food_sources: Vec::new(),
             food_source_found: RwLock::new(false),
             food_returned_home: RwLock::new(false),
+            trip_lengths: RwLock::new(Vec::new()),
+            metrics: Metrics::default(),
+            trails: TrailAnalysis::new(TRAIL_EVERY),
+            target_deliveries: None,
+            error: Arc::new(Mutex::new(None)),
             step: 0,
         }
     }
 
+    // Place the food sources still on the field, the nests and the obstacles in obstacles_grid.
+    // The grid only changes when a food source is depleted, and is then rebuilt from scratch: once
+    // updated its write buffer is empty, so removing a single item from it would empty the field.
+    fn place_items(&mut self) {
+        self.obstacles_grid = SparseGrid2D::new(self.width, self.height);
+        for (i, source) in self.food_sources.iter().enumerate() {
+            if source.on_field {
+                self.obstacles_grid.set_object_location(
+                    Item {
+                        id: FOOD_ID + i as u32,
+                        value: ItemType::Food,
+                    },
+                    &source.loc,
+                );
+            }
+        }
+        for (i, nest) in self.nests.iter().enumerate() {
+            self.obstacles_grid.set_object_location(
+                Item {
+                    id: NEST_ID + i as u32,
+                    value: ItemType::Home,
+                },
+                &nest.loc,
+            );
+        }
+        for (obstacle_id, obstacle_location) in self.map.obstacles.iter().enumerate() {
+            self.obstacles_grid.set_object_location(
+                Item {
+                    id: obstacle_id as u32,
+                    value: ItemType::Obstacle,
+                },
+                obstacle_location,
+            );
+        }
+        self.obstacles_grid.update();
+    }
+
+    // Remove from the field the food sources whose food is over, so that ants stop finding food there.
+    fn remove_depleted_food(&mut self) {
+        let mut removed = false;
+        for source in self.food_sources.iter_mut() {
+            if source.on_field && source.food() == 0 {
+                source.on_field = false;
+                removed = true;
+            }
+        }
+        if removed {
+            self.place_items();
+        }
+    }
+
+    // Remove the ants died during the step from the schedule, returning how many. A dead ant does
+    // not move in its last step, so it leaves the field when the field is updated.
+    // Ants are scheduled in the order of their ids, which are also their ids in the schedule.
+    fn remove_dead_ants(&mut self, schedule: &mut Schedule) -> u32 {
+        let mut dead_ants = std::mem::take(self.dead_ants.get_mut().unwrap());
+        dead_ants.sort_unstable_by_key(|ant| ant.id);
+        for ant in dead_ants.iter() {
+            dequeue(schedule, *ant);
+        }
+        self.population -= dead_ants.len() as u32;
+        dead_ants.len() as u32
+    }
+
+    // Spawn new ants in the nests with enough food in store, returning how many.
+    fn spawn_ants(&mut self, schedule: &mut Schedule) -> u32 {
+        self.new_ants.clear();
+        for i in 0..self.nests.len() {
+            while self.nests[i].spend(self.spawn_cost) {
+                let loc = self.nests[i].loc;
+                let mut ant = Ant::new(self.next_id, loc, false, 1.);
+                ant.caste = self.caste_of(ant.id);
+                // The first trip of the ant begins with the next step
+                ant.trip_start = schedule_step(schedule) + 1;
+                self.next_id += 1;
+                self.ants_grid.set_object_location(ant, &loc);
+                schedule.schedule_repeating(Box::new(ant), schedule.time + 1.0, 0);
+                self.new_ants.push(ant);
+            }
+        }
+        self.population += self.new_ants.len() as u32;
+        self.new_ants.len() as u32
+    }
+
+    // Caste of an ant. Ids are split in cycles as long as the total size of the castes, and each
+    // caste takes as many consecutive ids of a cycle as its size.
+    fn caste_of(&self, ant_id: u32) -> usize {
+        let total: u32 = self.castes.iter().map(|(size, _)| size).sum();
+        let mut id = ant_id % total.max(1);
+        for (caste, (size, _)) in self.castes.iter().enumerate() {
+            if id < *size {
+                return caste;
+            }
+            id -= size;
+        }
+        0
+    }
+
+    // Stop the run with an error, unless it has already been stopped by another one.
+    fn fail(&self, err: String) {
+        self.error.lock().unwrap().get_or_insert(err);
+    }
+
+    // Total food delivered to the nests.
+    pub fn delivered_food(&self) -> u32 {
+        self.nests.iter().map(|nest| nest.delivered()).sum()
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        Bounds {
+            width: self.width,
+            height: self.height,
+            toroidal: self.toroidal,
+        }
+    }
+
+    // The cells an ant on `loc` can step to: the ones of the 3x3 area around it inside the field,
+    // without obstacles.
+    pub fn free_neighbours(&self, loc: &Int2D) -> impl Iterator<Item = Neighbour> + '_ {
+        self.bounds().area(*loc).filter(move |neighbour| {
+            (neighbour.dx, neighbour.dy) != (0, 0) && self.get_obstacle(&neighbour.loc).is_none()
+        })
+    }
+
+    // The cell at the given offset from `loc`, if an ant can step there.
+    pub fn free_cell(&self, loc: &Int2D, dx: i32, dy: i32) -> Option<Int2D> {
+        self.bounds()
+            .offset(loc, dx, dy)
+            .filter(|cell| self.get_obstacle(cell).is_none())
+    }
+
     // Check if a particular grid cell has an obstacle or not. Will return None if the grid cell holds no obstacle.
     pub fn get_obstacle(&self, loc: &Int2D) -> Option<Vec<Item>> {
         self.obstacles_grid
@@ -235,3 +431,127 @@ impl ModelState {
             .filter(|vec| vec.first().unwrap().value == ItemType::Obstacle)
     }
 }
+
+// The parallel schedule has no dequeue, and counts its steps with another type. These helpers hide
+// the differences from the model.
+#[cfg(not(feature = "parallel"))]
+fn dequeue(schedule: &mut Schedule, ant: Ant) {
+    schedule.dequeue(Box::new(ant), ant.id);
+}
+
+#[cfg(feature = "parallel")]
+fn dequeue(schedule: &mut Schedule, ant: Ant) {
+    schedule
+        .events
+        .lock()
+        .unwrap()
+        .remove(&AgentImpl::new(Box::new(ant), ant.id));
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn schedule_step(schedule: &Schedule) -> u64 {
+    schedule.step as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item_at(state: &ModelState, x: i32, y: i32) -> Option<ItemType> {
+        state
+            .obstacles_grid
+            .get_objects(&Int2D { x, y })
+            .map(|items| items[0].value)
+    }
+
+    fn small_map() -> Map {
+        Map {
+            width: 10,
+            height: 10,
+            obstacles: vec![Int2D { x: 5, y: 5 }, Int2D { x: 5, y: 6 }],
+            nests: vec![Int2D { x: 1, y: 1 }],
+            food_sources: vec![(Int2D { x: 8, y: 8 }, 1), (Int2D { x: 8, y: 1 }, 10)],
+        }
+    }
+
+    // /dev/full accepts the file and fails every write
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unwritable_metrics_stop_the_run() {
+        let mut state = ModelState::new(small_map());
+        state.num_ants = 1;
+        state.metrics = Metrics::create("/dev/full").unwrap();
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        assert!(!state.end_condition(&mut schedule));
+        schedule.step(&mut state);
+        assert!(state.end_condition(&mut schedule));
+        let error = state.error.lock().unwrap().clone().unwrap();
+        assert!(error.starts_with("Unable to write the foraging metrics"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unwritable_trail_analysis_stops_the_run() {
+        let mut state = ModelState::new(small_map());
+        state.num_ants = 1;
+        state.trails = TrailAnalysis::create("/dev/full", 2).unwrap();
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        schedule.step(&mut state);
+        assert!(!state.end_condition(&mut schedule));
+        schedule.step(&mut state);
+        schedule.step(&mut state);
+        assert!(state.end_condition(&mut schedule));
+        let error = state.error.lock().unwrap().clone().unwrap();
+        assert!(error.starts_with("Unable to write the trail analysis"));
+    }
+
+    #[test]
+    fn deliveries_are_written_for_each_nest() {
+        let mut map = small_map();
+        map.nests.push(Int2D { x: 1, y: 8 });
+        let path = std::env::temp_dir().join(format!("ants_metrics_{}.csv", std::process::id()));
+        let mut state = ModelState::new(map);
+        state.num_ants = 1;
+        state.metrics = Metrics::create(&path).unwrap();
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        state.nests[1].deliver();
+        schedule.step(&mut state);
+
+        let output = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(
+            ",colony_food,nest_0_deliveries,nest_0_delivered,nest_1_deliveries,nest_1_delivered"
+        ));
+        assert!(lines[1].ends_with(",0,0,1,1"));
+    }
+
+    #[test]
+    fn depleted_food_leaves_the_other_sites() {
+        let mut state = ModelState::new(small_map());
+        state.num_ants = 1;
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        state.update(1);
+        assert_eq!(item_at(&state, 8, 8), Some(ItemType::Food));
+
+        assert!(state.food_sources[0].take());
+        state.update(2);
+        assert!(!state.food_sources[0].on_field);
+        assert_eq!(item_at(&state, 8, 8), None);
+        assert_eq!(item_at(&state, 8, 1), Some(ItemType::Food));
+        assert_eq!(item_at(&state, 1, 1), Some(ItemType::Home));
+        assert!(state.get_obstacle(&Int2D { x: 5, y: 5 }).is_some());
+        assert!(state.get_obstacle(&Int2D { x: 5, y: 6 }).is_some());
+
+        // The field stays the same in the following steps
+        state.update(3);
+        assert_eq!(item_at(&state, 8, 1), Some(ItemType::Food));
+        assert_eq!(item_at(&state, 1, 1), Some(ItemType::Home));
+        assert!(state.get_obstacle(&Int2D { x: 5, y: 5 }).is_some());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::model::ant::Task;
+use crate::model::pheromone;
+use crate::model::state::ModelState;
+use krabmaga::engine::location::Int2D;
+use krabmaga::hashbrown::{HashMap, HashSet};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::path::Path;
+
+// Quality of the trails between a nest and a food source, one row of the output file. Trails are
+// extracted by following the gradient of the pheromone, from the nest to the food with the
+// pheromones followed by the ants looking for food (outbound), and back with the ones followed by
+// the ants carrying it (return). Lengths are in steps, as ants move to one of the 8 cells around
+// them, and they are empty when the gradient does not lead to the destination.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct TrailRecord {
+    pub run: u32,
+    pub step: u64,
+    pub nest: usize,
+    pub food_source: usize,
+    // Shortest path around the obstacles, empty if the food cannot be reached
+    pub shortest_length: Option<u32>,
+    pub outbound_length: Option<u32>,
+    pub return_length: Option<u32>,
+    // Trail lengths over the shortest one, 1 for an optimal trail
+    pub outbound_ratio: Option<f64>,
+    pub return_ratio: Option<f64>,
+    // Entropy of the distribution of the pheromone followed in each direction over the free cells
+    // of the field, from 0 when all the pheromone is on a single cell to 1 when it is spread
+    // evenly: the lower, the sharper the trails
+    pub outbound_entropy: f64,
+    pub return_entropy: f64,
+}
+
+// Trail analysis of a run, done every `every` steps (never with 0). The records are written to a
+// CSV file, if any, and the cells of the last trails are kept for the visualization.
+#[derive(Default)]
+pub struct TrailAnalysis {
+    pub every: u64,
+    // Size of the field, for the overlay
+    pub width: i32,
+    pub height: i32,
+    // Cells of the trails and of the shortest paths found by the last analysis
+    pub trails: HashSet<Int2D>,
+    pub shortest_paths: HashSet<Int2D>,
+    writer: Option<csv::Writer<File>>,
+}
+
+impl TrailAnalysis {
+    pub fn new(every: u64) -> TrailAnalysis {
+        TrailAnalysis {
+            every,
+            ..TrailAnalysis::default()
+        }
+    }
+
+    pub fn create<P: AsRef<Path>>(path: P, every: u64) -> Result<TrailAnalysis, String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+        }
+        let writer = csv::Writer::from_path(path)
+            .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+        Ok(TrailAnalysis {
+            every,
+            writer: Some(writer),
+            ..TrailAnalysis::default()
+        })
+    }
+
+    pub fn is_due(&self, step: u64) -> bool {
+        self.every > 0 && step > 0 && step.is_multiple_of(self.every)
+    }
+
+    // Forget the trails of the previous repetition. The rows of all the repetitions go in the same
+    // file.
+    pub fn reset(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+        self.trails.clear();
+        self.shortest_paths.clear();
+    }
+
+    // Write the records of an analysis. The file is closed on the first error, which stops the run.
+    pub fn record(&mut self, records: &[TrailRecord]) -> Result<(), String> {
+        if let Some(writer) = self.writer.as_mut() {
+            let written = records
+                .iter()
+                .try_for_each(|record| writer.serialize(record))
+                .and_then(|_| writer.flush().map_err(csv::Error::from));
+            if let Err(e) = written {
+                self.writer = None;
+                return Err(format!("Unable to write the trail analysis: {}", e));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Analyse the trails between every nest and every food source still on the field. Returns the
+// records, the cells of the trails and the cells of the shortest paths.
+pub fn analyse(
+    state: &ModelState,
+    run: u32,
+    step: u64,
+) -> (Vec<TrailRecord>, Vec<Int2D>, Vec<Int2D>) {
+    // No trail is extracted for a task without pheromones to follow
+    let followed = |task| {
+        let follows = state.pheromones.followed_by(task).next().is_some();
+        follows.then_some(task)
+    };
+    let outbound = followed(Task::Searching);
+    let inbound = followed(Task::Returning);
+    let blocked = blocked_cells(state);
+    let free_cells = blocked.iter().filter(|blocked| !**blocked).count();
+    let outbound_entropy = outbound.map_or(0., |task| entropy(state, task, free_cells));
+    let return_entropy = inbound.map_or(0., |task| entropy(state, task, free_cells));
+
+    let mut records = Vec::new();
+    let mut trails = Vec::new();
+    let mut shortest_paths = Vec::new();
+    for (n, nest) in state.nests.iter().enumerate() {
+        let parents = shortest_paths_from(state, &blocked, &nest.loc);
+        for (f, source) in state.food_sources.iter().enumerate() {
+            if !source.on_field {
+                continue;
+            }
+            let shortest = path_to(state, &parents, &nest.loc, &source.loc);
+            let outbound_trail =
+                outbound.and_then(|task| follow(state, &blocked, task, &nest.loc, &source.loc));
+            let return_trail =
+                inbound.and_then(|task| follow(state, &blocked, task, &source.loc, &nest.loc));
+
+            let length =
+                |path: &Option<Vec<Int2D>>| path.as_ref().map(|path| path.len() as u32 - 1);
+            let shortest_length = length(&shortest);
+            let ratio = |trail: Option<u32>| match (trail, shortest_length) {
+                (Some(trail), Some(shortest)) if shortest > 0 => {
+                    Some(trail as f64 / shortest as f64)
+                }
+                _ => None,
+            };
+            let outbound_length = length(&outbound_trail);
+            let return_length = length(&return_trail);
+            records.push(TrailRecord {
+                run,
+                step,
+                nest: n,
+                food_source: f,
+                shortest_length,
+                outbound_length,
+                return_length,
+                outbound_ratio: ratio(outbound_length),
+                return_ratio: ratio(return_length),
+                outbound_entropy,
+                return_entropy,
+            });
+
+            trails.extend(outbound_trail.into_iter().flatten());
+            trails.extend(return_trail.into_iter().flatten());
+            shortest_paths.extend(shortest.into_iter().flatten());
+        }
+    }
+    (records, trails, shortest_paths)
+}
+
+fn index(state: &ModelState, loc: &Int2D) -> usize {
+    (loc.x * state.height + loc.y) as usize
+}
+
+// Cells of the obstacles grid holding an obstacle, column by column.
+fn blocked_cells(state: &ModelState) -> Vec<bool> {
+    (0..state.width)
+        .flat_map(|x| (0..state.height).map(move |y| Int2D { x, y }))
+        .map(|loc| state.get_obstacle(&loc).is_some())
+        .collect()
+}
+
+// Breadth-first search over the free cells of the field from `start`: the cell each reachable cell
+// is reached from on a shortest path.
+fn shortest_paths_from(state: &ModelState, blocked: &[bool], start: &Int2D) -> Vec<Option<Int2D>> {
+    let mut parents = vec![None; blocked.len()];
+    parents[index(state, start)] = Some(*start);
+    let mut queue = VecDeque::from([*start]);
+    while let Some(loc) = queue.pop_front() {
+        for neighbour in state.bounds().area(loc) {
+            let i = index(state, &neighbour.loc);
+            if !blocked[i] && parents[i].is_none() {
+                parents[i] = Some(loc);
+                queue.push_back(neighbour.loc);
+            }
+        }
+    }
+    parents
+}
+
+// Shortest path from `start` to `end`, both included, given the result of the search from `start`.
+fn path_to(
+    state: &ModelState,
+    parents: &[Option<Int2D>],
+    start: &Int2D,
+    end: &Int2D,
+) -> Option<Vec<Int2D>> {
+    parents[index(state, end)]?;
+    let mut path = vec![*end];
+    let mut loc = *end;
+    while loc != *start {
+        loc = parents[index(state, &loc)]?;
+        path.push(loc);
+    }
+    path.reverse();
+    Some(path)
+}
+
+// Trail from `start` to `end`, both included, following the gradient of the pheromone sensed by the
+// ants doing `task`: at each step the free cell around with the most pheromone, never going back to
+// a cell already on the trail. None if the pheromone runs out or the trail gets stuck before
+// reaching `end`.
+fn follow(
+    state: &ModelState,
+    blocked: &[bool],
+    task: Task,
+    start: &Int2D,
+    end: &Int2D,
+) -> Option<Vec<Int2D>> {
+    let mut visited = vec![false; blocked.len()];
+    visited[index(state, start)] = true;
+    let mut trail = vec![*start];
+    let mut loc = *start;
+    while loc != *end {
+        let mut next = None;
+        let mut max = 0.;
+        for neighbour in state.bounds().area(loc) {
+            let i = index(state, &neighbour.loc);
+            if blocked[i] || visited[i] {
+                continue;
+            }
+            if neighbour.loc == *end {
+                // The destination is a site, reached as soon as it is next to the trail
+                next = Some(neighbour.loc);
+                break;
+            }
+            let value = state.pheromones.scent(task, &neighbour.loc);
+            if value > max {
+                max = value;
+                next = Some(neighbour.loc);
+            }
+        }
+        loc = next?;
+        visited[index(state, &loc)] = true;
+        trail.push(loc);
+    }
+    Some(trail)
+}
+
+// Normalized Shannon entropy of the pheromone sensed by the ants doing `task` over `cells` cells.
+fn entropy(state: &ModelState, task: Task, cells: usize) -> f64 {
+    let mut scent: HashMap<Int2D, f64> = HashMap::new();
+    for layer in state.pheromones.followed_by(task) {
+        for (loc, value) in pheromone::cells(&layer.grid) {
+            *scent.entry(loc).or_insert(0.) += value as f64;
+        }
+    }
+    let values: Vec<f64> = scent.into_values().collect();
+    let total: f64 = values.iter().sum();
+    if total <= 0. || cells < 2 {
+        return 0.;
+    }
+    let entropy: f64 = values
+        .iter()
+        .map(|value| {
+            let p = value / total;
+            -p * p.ln()
+        })
+        .sum();
+    entropy / (cells as f64).ln()
+}
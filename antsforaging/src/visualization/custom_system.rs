@@ -1,7 +1,7 @@
+use crate::model::pheromone::Pheromones;
 use crate::model::state::ModelState;
 use crate::model::state::*;
-use crate::model::to_food_grid::ToFoodGrid;
-use crate::model::to_home_grid::ToHomeGrid;
+use crate::model::trail::TrailAnalysis;
 use krabmaga::bevy::prelude::Image;
 use krabmaga::engine::location::Int2D;
 use krabmaga::visualization::fields::number_grid_2d::BatchRender;
@@ -10,12 +10,16 @@ use krabmaga::engine::fields::dense_object_grid_2d::DenseGrid2D;
 use krabmaga::engine::fields::sparse_object_grid_2d::SparseGrid2D;
 use krabmaga::visualization::fields::object_grid_2d::RenderObjectGrid2D;
 
-impl BatchRender<ModelState> for ToHomeGrid {
+// All the pheromone layers are drawn in a single texture: each cell takes the colour of the layer
+// with the most pheromone on it.
+impl BatchRender<ModelState> for Pheromones {
     fn get_pixel(&self, loc: &Int2D) -> [u8; 4] {
-        match self.grid.get_value(loc) {
-            Some(val) => {
-                let cell = val;
-
+        let mut pixel = [0u8, 0u8, 0u8, 0u8];
+        let mut max = 0.;
+        for layer in self.layers.iter() {
+            let cell = layer.value(loc);
+            if cell > max {
+                max = cell;
                 let alpha = if cell < 0.01 {
                     50u8
                 } else if cell < 0.1 {
@@ -23,14 +27,15 @@ impl BatchRender<ModelState> for ToHomeGrid {
                 } else {
                     255u8
                 };
-                [0u8, 255u8, 0u8, alpha]
+                let [r, g, b] = layer.config.color;
+                pixel = [r, g, b, alpha];
             }
-            None => [0u8, 255u8, 0u8, 0u8],
         }
+        pixel
     }
 
     fn get_dimensions(&self) -> (u32, u32) {
-        (self.grid.width as u32, self.grid.height as u32)
+        (self.width as u32, self.height as u32)
     }
 
     fn get_layer(&self) -> f32 {
@@ -38,39 +43,32 @@ impl BatchRender<ModelState> for ToHomeGrid {
     }
 
     fn get_texture_from_state(state: &ModelState) -> Image {
-        state.to_home_grid.texture()
+        state.pheromones.texture()
     }
 }
 
-impl BatchRender<ModelState> for ToFoodGrid {
+// Overlay of the last trail analysis, drawn over the pheromones: the trails following the gradients
+// in magenta, the shortest paths in yellow and the cells on both in white.
+impl BatchRender<ModelState> for TrailAnalysis {
     fn get_pixel(&self, loc: &Int2D) -> [u8; 4] {
-        match self.grid.get_value(loc) {
-            Some(val) => {
-                let cell = val;
-
-                let alpha = if cell < 0.01 {
-                    50u8
-                } else if cell < 0.1 {
-                    80u8
-                } else {
-                    255u8
-                };
-                [0u8, 0u8, 255u8, alpha]
-            }
-            None => [0u8, 0u8, 255u8, 0u8],
+        match (self.trails.contains(loc), self.shortest_paths.contains(loc)) {
+            (true, true) => [255u8, 255u8, 255u8, 255u8],
+            (true, false) => [255u8, 0u8, 255u8, 255u8],
+            (false, true) => [255u8, 255u8, 0u8, 255u8],
+            (false, false) => [0u8, 0u8, 0u8, 0u8],
         }
     }
 
     fn get_dimensions(&self) -> (u32, u32) {
-        (self.grid.width as u32, self.grid.height as u32)
+        (self.width as u32, self.height as u32)
     }
 
     fn get_layer(&self) -> f32 {
-        0.
+        0.1
     }
 
     fn get_texture_from_state(state: &ModelState) -> Image {
-        state.to_food_grid.texture()
+        state.trails.texture()
     }
 }
 
@@ -4,7 +4,7 @@ use krabmaga::engine::fields::sparse_object_grid_2d::SparseGrid2D;
 use krabmaga::engine::location::Int2D;
 use krabmaga::engine::schedule::Schedule;
 use krabmaga::engine::state::State as StateTrait;
-use krabmaga::visualization::agent_render::AgentRender;
+use krabmaga::visualization::agent_render::{AgentRender, SpriteType};
 use krabmaga::visualization::asset_handle_factory::AssetHandleFactoryResource;
 use krabmaga::visualization::fields::number_grid_2d::BatchRender;
 use krabmaga::visualization::fields::object_grid_2d::RenderObjectGrid2D;
@@ -29,8 +29,8 @@ impl VisualizationState<ModelState> for VisState {
         _schedule: &mut Schedule,
         sim: &mut SimulationDescriptor,
     ) {
-        state.to_home_grid.render(sprite_factory, commands, sim);
-        state.to_food_grid.render(sprite_factory, commands, sim);
+        state.pheromones.render(sprite_factory, commands, sim);
+        state.trails.render(sprite_factory, commands, sim);
         SparseGrid2D::<Item>::init_graphics_grid(sprite_factory, commands, state);
     }
 
@@ -69,11 +69,26 @@ impl VisualizationState<ModelState> for VisState {
 
     fn before_render(
         &mut self,
-        _state: &mut ModelState,
+        state: &mut ModelState,
         _schedule: &Schedule,
-        _commands: &mut Commands,
-        _sprite_factory: &mut AssetHandleFactoryResource,
+        commands: &mut Commands,
+        sprite_factory: &mut AssetHandleFactoryResource,
     ) {
+        // Ants spawned by the colony during the last step
+        for ant in &state.new_ants {
+            let boxed_agent = &ant.as_agent();
+            let boxed_state = Box::new(state.as_state());
+            let ant_vis = self.get_agent_render(boxed_agent, state);
+            let SpriteType::Emoji(emoji_code) = ant_vis.unwrap().sprite(boxed_agent, &boxed_state);
+            let sprite_render = sprite_factory.get_emoji_loader(emoji_code);
+            self.setup_agent_graphics(
+                boxed_agent,
+                self.get_agent_render(boxed_agent, state).unwrap(),
+                sprite_render,
+                commands,
+                &boxed_state,
+            );
+        }
     }
 }
 
@@ -1,13 +1,13 @@
 // No visualization specific imports
 #[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
 use {
+    crate::model::exploration::{ExplorationSettings, FlockerExploration, SETTINGS},
+    crate::model::metrics::Metrics,
+    crate::model::state_3d::Flocker3D,
+    crate::model::trajectory::Trajectory,
     krabmaga::engine::schedule::Schedule,
-    // krabmaga::*,
     krabmaga::engine::state::State,
-    krabmaga::simulate,
-    krabmaga::Info,
     krabmaga::*,
-    std::time::Duration,
 };
 // Visualization specific imports
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
@@ -16,35 +16,182 @@ use {
     krabmaga::visualization::visualization::Visualization,
 };
 
+use crate::model::params::FlockerParams;
 use crate::model::state::Flocker;
+use crate::model::steering::Steering;
 
 mod model;
 
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
 mod visualization;
 
-pub static COHESION: f32 = 0.8;
-pub static AVOIDANCE: f32 = 1.0;
-pub static RANDOMNESS: f32 = 1.1;
-pub static CONSISTENCY: f32 = 0.7;
-pub static MOMENTUM: f32 = 1.0;
-pub static JUMP: f32 = 0.7;
-pub static DISCRETIZATION: f32 = 10.0 / 1.5;
-pub static TOROIDAL: bool = true;
+fn exit_with_error(err: String) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
+
+// Value of `<name> <value>` on the command line, if given.
+fn arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let i = args.iter().position(|arg| arg == name)?;
+    match args.get(i + 1) {
+        Some(value) => Some(value.clone()),
+        None => exit_with_error(format!("Missing value for {}", name)),
+    }
+}
+
+// Parameters of the birds: defaults, then an optional `--config <file>`, then single
+// `--<name> <value>` overrides.
+fn load_params() -> FlockerParams {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    FlockerParams::default()
+        .with_args(
+            &args,
+            &["model", "output", "explore", "dimensions", "trajectory"],
+        )
+        .unwrap_or_else(|err| exit_with_error(err))
+}
+
+// Steering model of the birds given with `--model <boids|vicsek|couzin>`, Boids by default.
+fn load_steering(params: &FlockerParams) -> Steering {
+    match arg("--model").as_deref() {
+        None | Some("boids") => Steering::Boids {
+            radius: params.neighbour_radius,
+        },
+        Some("vicsek") => Steering::Vicsek {
+            radius: params.neighbour_radius,
+            noise: params.vicsek_noise,
+        },
+        Some("couzin") => Steering::Couzin {
+            repulsion: params.couzin_repulsion,
+            orientation: params.couzin_orientation,
+            attraction: params.couzin_attraction,
+            blind_angle: params.couzin_blind_angle,
+            turning_rate: params.couzin_turning_rate,
+            noise: params.couzin_noise,
+        },
+        Some(name) => exit_with_error(format!(
+            "Invalid value for --model: {}, expected boids, vicsek or couzin",
+            name
+        )),
+    }
+}
 
 // Main used when only the simulation should run, without any visualization.
 #[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
 fn main() {
-    use krabmaga::simulate_old;
-
     let step = 200;
 
     let dim = (100., 100.);
     let num_agents = 1000;
-    let state = Flocker::new(dim, num_agents);
+    let params = load_params();
+    if let Some(name) = arg("--explore") {
+        explore(step, dim, num_agents, &params, &name);
+        return;
+    }
+    match arg("--dimensions").as_deref() {
+        None | Some("2") => {}
+        Some("3") => {
+            simulate_3d(step, num_agents, params);
+            return;
+        }
+        Some(dimensions) => exit_with_error(format!(
+            "Invalid value for --dimensions: {}, expected 2 or 3",
+            dimensions
+        )),
+    }
+    if arg("--trajectory").is_some() {
+        exit_with_error(String::from("--trajectory requires --dimensions 3"));
+    }
+    let mut state = Flocker::new(dim, num_agents, params);
+    state.steering = load_steering(&state.params);
+    // Flock metrics of every step with `--output <csv>`
+    if let Some(output) = arg("--output") {
+        state.metrics = Metrics::create(output).unwrap_or_else(|err| exit_with_error(err));
+    }
     // let _ = simulate!(state, step, 1, Info::Normal);
     // let _ = simulate!(state, step, 1);
+    let error = state.error.clone();
     let _ = simulate_old!(state, step, 1, krabmaga::Info::Normal);
+    let error = error.lock().unwrap().take();
+    if let Some(err) = error {
+        exit_with_error(err);
+    }
+}
+
+// The 3D variant with `--dimensions 3`. Birds fly in a cube where they have about as many
+// neighbours within the neighbour radius as in the 2D field (34 and 31 on average with the default
+// parameters), without predators nor obstacles. Metrics are written with `--output <csv>` as in
+// 2D, and the locations of the birds with `--trajectory <csv|xyz>`.
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+fn simulate_3d(step: u64, num_agents: u32, params: FlockerParams) {
+    if params.predators > 0 || params.obstacles {
+        exit_with_error(String::from(
+            "--predators and --obstacles require --dimensions 2",
+        ));
+    }
+    let dim = (50., 50., 50.);
+    let mut state = Flocker3D::new(dim, num_agents, params);
+    state.steering = load_steering(&state.params);
+    if let Some(output) = arg("--output") {
+        state.metrics = Metrics::create(output).unwrap_or_else(|err| exit_with_error(err));
+    }
+    if let Some(trajectory) = arg("--trajectory") {
+        state.trajectory =
+            Trajectory::create(trajectory).unwrap_or_else(|err| exit_with_error(err));
+    }
+    let error = state.error.clone();
+    let _ = simulate_old!(state, step, 1, krabmaga::Info::Normal);
+    let error = error.lock().unwrap().take();
+    if let Some(err) = error {
+        exit_with_error(err);
+    }
+}
+
+// Sweep the cohesion, avoidance and consistency weights of the Boids rule with
+// `--explore <name>`, starting from `params`. Every combination runs once, in parallel with the
+// others, with the predators and obstacles enabled in `params` if any, and its order parameters and
+// flocks after the last step are written in `<name>.csv`.
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+// The row type built by explore_parallel! has a constructor with all the inputs and outputs, and
+// the repetitions are counted modulo 1
+#[allow(clippy::too_many_arguments, clippy::modulo_one)]
+fn explore(step: u64, dim: (f32, f32), num_agents: u32, params: &FlockerParams, name: &str) {
+    *SETTINGS.lock().unwrap() = ExplorationSettings {
+        last_step: step,
+        dim,
+        initial_flockers: num_agents,
+        params: params.clone(),
+    };
+    // 0, 0.5, .., 2 for each weight
+    let weights: Vec<f32> = (0..5).map(|i| i as f32 * 0.5).collect();
+    let cohesion = weights.clone();
+    let avoidance = weights.clone();
+    let consistency = weights;
+    let step = step as u32;
+
+    let result = explore_parallel!(
+        step,
+        1,
+        FlockerExploration,
+        input {
+            cohesion: f32
+            avoidance: f32
+            consistency: f32
+        },
+        output [
+            polarization: f64
+            milling: f64
+            nnd_mean: f64
+            flocks: u32
+            largest_flock: u32
+        ],
+        ExploreMode::Exaustive,
+    );
+
+    if let Err(err) = write_csv(name, &result) {
+        exit_with_error(format!("Unable to write {}.csv: {}", name, err));
+    }
 }
 
 // Main used when a visualization feature is applied.
@@ -52,7 +199,8 @@ fn main() {
 fn main() {
     let dim = (200., 200.);
     let num_agents = 100;
-    let state = Flocker::new(dim, num_agents);
+    let mut state = Flocker::new(dim, num_agents, load_params());
+    state.steering = load_steering(&state.params);
     Visualization::default()
         .with_window_dimensions(1000., 700.)
         .with_simulation_dimensions(dim.0, dim.1)
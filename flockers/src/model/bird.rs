@@ -4,11 +4,10 @@ use krabmaga::engine::fields::field_2d::{toroidal_distance, toroidal_transform,
 use krabmaga::engine::location::Real2D;
 use krabmaga::engine::state::State;
 use krabmaga::rand;
-use krabmaga::rand::Rng;
 use std::hash::{Hash, Hasher};
 
 use crate::model::state::Flocker;
-use crate::{AVOIDANCE, COHESION, CONSISTENCY, JUMP, MOMENTUM, RANDOMNESS};
+use crate::model::steering::Neighbour;
 
 #[derive(Clone, Copy)]
 pub struct Bird {
@@ -28,108 +27,55 @@ impl Agent for Bird {
         let state = state.as_any().downcast_ref::<Flocker>().unwrap();
         let vec = state
             .field1
-            .get_neighbors_within_relax_distance(self.loc, 10.0);
+            .get_neighbors_within_relax_distance(self.loc, state.steering.radius());
 
         let width = state.dim.0;
         let height = state.dim.1;
 
-        let mut avoidance = Real2D { x: 0.0, y: 0.0 };
-
-        let mut cohesion = Real2D { x: 0.0, y: 0.0 };
-        let mut randomness = Real2D { x: 0.0, y: 0.0 };
-        let mut consistency = Real2D { x: 0.0, y: 0.0 };
-
-        if !vec.is_empty() {
-            //avoidance
-            let mut x_avoid = 0.0;
-            let mut y_avoid = 0.0;
-            let mut x_cohe = 0.0;
-            let mut y_cohe = 0.0;
-            let mut x_cons = 0.0;
-            let mut y_cons = 0.0;
-            let mut count = 0;
-
-            for elem in &vec {
-                if self.id != elem.id {
-                    let dx = toroidal_distance(self.loc.x, elem.loc.x, width);
-                    let dy = toroidal_distance(self.loc.y, elem.loc.y, height);
-                    count += 1;
-
-                    //avoidance calculation
-                    let square = dx * dx + dy * dy;
-                    x_avoid += dx / (square * square + 1.0);
-                    y_avoid += dy / (square * square + 1.0);
-
-                    //cohesion calculation
-                    x_cohe += dx;
-                    y_cohe += dy;
-
-                    //consistency calculation
-                    x_cons += elem.last_d.x;
-                    y_cons += elem.last_d.y;
-                }
+        let neighbours: Vec<Neighbour> = vec
+            .iter()
+            .filter(|elem| self.id != elem.id && !state.caught_birds.contains(*elem))
+            .map(|elem| Neighbour {
+                bird: *elem,
+                dx: toroidal_distance(self.loc.x, elem.loc.x, width),
+                dy: toroidal_distance(self.loc.y, elem.loc.y, height),
+            })
+            .collect();
+        let mut rng = rand::rng();
+        let direction = state
+            .steering
+            .direction(self, &neighbours, &state.params, &mut rng);
+
+        //fleeing from the predators in sight, harder from the closest ones. Without predators the
+        //field is not looked up: the one of the parallel feature divides by the number of objects
+        let params = &state.params;
+        let mut flee = Real2D { x: 0.0, y: 0.0 };
+        let predators = if params.predators > 0 {
+            state
+                .predators
+                .get_neighbors_within_relax_distance(self.loc, params.flee_radius)
+        } else {
+            Vec::new()
+        };
+        for predator in predators {
+            let dx = toroidal_distance(self.loc.x, predator.loc.x, width);
+            let dy = toroidal_distance(self.loc.y, predator.loc.y, height);
+            let dis = (dx * dx + dy * dy).sqrt();
+            if dis > 0.0 && dis < params.flee_radius {
+                flee.x += dx / dis * (1.0 - dis / params.flee_radius);
+                flee.y += dy / dis * (1.0 - dis / params.flee_radius);
             }
-
-            if count > 0 {
-                x_avoid /= count as f32;
-                y_avoid /= count as f32;
-                x_cohe /= count as f32;
-                y_cohe /= count as f32;
-                x_cons /= count as f32;
-                y_cons /= count as f32;
-
-                consistency = Real2D {
-                    x: x_cons / count as f32,
-                    y: y_cons / count as f32,
-                };
-            } else {
-                consistency = Real2D {
-                    x: x_cons,
-                    y: y_cons,
-                };
-            }
-
-            avoidance = Real2D {
-                x: 400.0 * x_avoid,
-                y: 400.0 * y_avoid,
-            };
-
-            cohesion = Real2D {
-                x: -x_cohe / 10.0,
-                y: -y_cohe / 10.0,
-            };
-
-            //randomness
-            let mut rng = rand::rng();
-            let r1: f32 = rng.random();
-            let x_rand = r1 * 2.0 - 1.0;
-            let r2: f32 = rng.random();
-            let y_rand = r2 * 2.0 - 1.0;
-
-            let square = (x_rand * x_rand + y_rand * y_rand).sqrt();
-            randomness = Real2D {
-                x: 0.05 * x_rand / square,
-                y: 0.05 * y_rand / square,
-            };
         }
 
-        let mom = self.last_d;
+        let obstacle = state.obstacles.avoidance(self.loc, params.obstacle_range);
 
-        let mut dx = COHESION * cohesion.x
-            + AVOIDANCE * avoidance.x
-            + CONSISTENCY * consistency.x
-            + RANDOMNESS * randomness.x
-            + MOMENTUM * mom.x;
-        let mut dy = COHESION * cohesion.y
-            + AVOIDANCE * avoidance.y
-            + CONSISTENCY * consistency.y
-            + RANDOMNESS * randomness.y
-            + MOMENTUM * mom.y;
+        let mut dx = direction.x + params.flee * flee.x + params.obstacle_avoidance * obstacle.x;
+        let mut dy = direction.y + params.flee * flee.y + params.obstacle_avoidance * obstacle.y;
 
         let dis = (dx * dx + dy * dy).sqrt();
         if dis > 0.0 {
-            dx = dx / dis * JUMP;
-            dy = dy / dis * JUMP;
+            dx = dx / dis * state.params.jump;
+            dy = dy / dis * state.params.jump;
         }
 
         self.last_d = Real2D { x: dx, y: dy };
@@ -137,11 +83,12 @@ impl Agent for Bird {
         let loc_x = toroidal_transform(self.loc.x + dx, width);
         let loc_y = toroidal_transform(self.loc.y + dy, height);
 
-        self.loc = Real2D { x: loc_x, y: loc_y };
+        //birds never enter the obstacles: the avoidance turns them around in the next steps
+        if !state.obstacles.contains(Real2D { x: loc_x, y: loc_y }) {
+            self.loc = Real2D { x: loc_x, y: loc_y };
+        }
         drop(vec);
-        state
-            .field1
-            .set_object_location(*self, Real2D { x: loc_x, y: loc_y });
+        state.field1.set_object_location(*self, self.loc);
     }
 }
 
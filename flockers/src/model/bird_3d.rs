@@ -0,0 +1,101 @@
+use core::fmt;
+use krabmaga::engine::agent::Agent;
+use krabmaga::engine::fields::field_2d::toroidal_transform;
+use krabmaga::engine::state::State;
+use krabmaga::rand;
+use std::hash::{Hash, Hasher};
+
+use crate::model::field_3d::Real3D;
+use crate::model::state_3d::Flocker3D;
+use crate::model::steering_3d::Neighbour3D;
+
+// A bird of the 3D variant: the same rules as Bird, with locations and headings in 3D.
+#[derive(Clone, Copy)]
+pub struct Bird3D {
+    pub id: u32,
+    pub loc: Real3D,
+    pub last_d: Real3D,
+}
+
+impl Bird3D {
+    pub fn new(id: u32, loc: Real3D, last_d: Real3D) -> Self {
+        Bird3D { id, loc, last_d }
+    }
+}
+
+impl Agent for Bird3D {
+    fn step(&mut self, state: &mut dyn State) {
+        let state = state.as_any().downcast_ref::<Flocker3D>().unwrap();
+        let neighbours: Vec<Neighbour3D> = state
+            .field1
+            .get_neighbors_within_relax_distance(self.loc, state.steering.radius())
+            .into_iter()
+            .filter(|elem| self.id != elem.id)
+            .map(|elem| Neighbour3D {
+                bird: elem,
+                offset: state.field1.offset(elem.loc, self.loc),
+            })
+            .collect();
+        let mut rng = rand::rng();
+        let direction = state
+            .steering
+            .direction_3d(self, &neighbours, &state.params, &mut rng);
+
+        let dis = direction.length();
+        self.last_d = if dis > 0.0 {
+            direction * (state.params.jump / dis)
+        } else {
+            direction
+        };
+
+        let (width, height, length) = state.dim;
+        let toroidal = state.params.toroidal;
+        self.loc = Real3D {
+            x: advance(self.loc.x, &mut self.last_d.x, width, toroidal),
+            y: advance(self.loc.y, &mut self.last_d.y, height, toroidal),
+            z: advance(self.loc.z, &mut self.last_d.z, length, toroidal),
+        };
+        state.field1.set_object_location(*self, self.loc);
+    }
+}
+
+// Coordinate reached moving by `d` from `loc` on an axis of side `dim`: across the edge of a
+// toroidal field, otherwise bouncing off the wall, which reverses the heading along the axis.
+fn advance(loc: f32, d: &mut f32, dim: f32, toroidal: bool) -> f32 {
+    let next = loc + *d;
+    if toroidal {
+        return toroidal_transform(next, dim);
+    }
+    if next < 0. {
+        *d = -*d;
+        -next
+    } else if next > dim {
+        *d = -*d;
+        2. * dim - next
+    } else {
+        next
+    }
+}
+
+impl Hash for Bird3D {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.id.hash(state);
+    }
+}
+
+impl Eq for Bird3D {}
+
+impl PartialEq for Bird3D {
+    fn eq(&self, other: &Bird3D) -> bool {
+        self.id == other.id
+    }
+}
+
+impl fmt::Display for Bird3D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} loc {}", self.id, self.loc)
+    }
+}
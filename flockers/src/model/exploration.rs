@@ -0,0 +1,112 @@
+use crate::model::metrics;
+use crate::model::params::FlockerParams;
+use crate::model::state::Flocker;
+use krabmaga::engine::schedule::Schedule;
+use krabmaga::engine::state::State;
+use krabmaga::lazy_static;
+use std::any::Any;
+use std::sync::Mutex;
+
+// Everything but the swept weights, the same for all the runs of an exploration.
+#[derive(Clone, Default)]
+pub struct ExplorationSettings {
+    pub last_step: u64,
+    pub dim: (f32, f32),
+    pub initial_flockers: u32,
+    pub params: FlockerParams,
+}
+
+lazy_static! {
+    // Set before the exploration starts: the macros create the states from the inputs only
+    pub static ref SETTINGS: Mutex<ExplorationSettings> = Mutex::new(ExplorationSettings::default());
+}
+
+// Flocker built by the model exploration macros of krabmaga, which create the state with
+// `new(<inputs>)` and read the inputs and the outputs back from its fields. The birds meet
+// predators and obstacles only if they are enabled in the parameters, and the agents keep
+// downcasting the state to the inner Flocker, returned by as_any.
+pub struct FlockerExploration {
+    // Swept weights of the Boids rule
+    pub cohesion: f32,
+    pub avoidance: f32,
+    pub consistency: f32,
+    // Order parameters and flocks after the last step, see FlockMetrics
+    pub polarization: f64,
+    pub milling: f64,
+    pub nnd_mean: f64,
+    pub flocks: u32,
+    pub largest_flock: u32,
+    // Step after which the outputs are measured
+    pub last_step: u64,
+    pub flocker: Flocker,
+}
+
+impl FlockerExploration {
+    pub fn new(cohesion: f32, avoidance: f32, consistency: f32) -> Self {
+        let settings = SETTINGS.lock().unwrap().clone();
+        let params = FlockerParams {
+            cohesion,
+            avoidance,
+            consistency,
+            ..settings.params
+        };
+        FlockerExploration {
+            cohesion,
+            avoidance,
+            consistency,
+            polarization: 0.,
+            milling: 0.,
+            nnd_mean: 0.,
+            flocks: 0,
+            largest_flock: 0,
+            last_step: settings.last_step,
+            flocker: Flocker::new(settings.dim, settings.initial_flockers, params),
+        }
+    }
+}
+
+impl State for FlockerExploration {
+    fn reset(&mut self) {
+        self.flocker.reset();
+    }
+
+    fn init(&mut self, schedule: &mut Schedule) {
+        self.flocker.init(schedule);
+    }
+
+    fn update(&mut self, step: u64) {
+        self.flocker.update(step);
+        if step == self.last_step {
+            let metrics = metrics::measure(&self.flocker, step);
+            self.polarization = metrics.polarization;
+            self.milling = metrics.milling;
+            self.nnd_mean = metrics.nnd_mean;
+            self.flocks = metrics.flocks;
+            self.largest_flock = metrics.largest_flock;
+        }
+    }
+
+    fn before_step(&mut self, schedule: &mut Schedule) {
+        self.flocker.before_step(schedule);
+    }
+
+    fn after_step(&mut self, schedule: &mut Schedule) {
+        self.flocker.after_step(schedule);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        &self.flocker
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        &mut self.flocker
+    }
+
+    fn as_state_mut(&mut self) -> &mut dyn State {
+        self
+    }
+
+    fn as_state(&self) -> &dyn State {
+        self
+    }
+}
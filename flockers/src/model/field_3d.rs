@@ -0,0 +1,173 @@
+use core::fmt;
+use krabmaga::engine::fields::field_2d::toroidal_distance;
+use krabmaga::hashbrown::HashMap;
+use std::ops::{Add, Mul, Sub};
+use std::sync::Mutex;
+
+// A point, or a vector, of the 3D space.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Real3D {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Real3D {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Real3D { x, y, z }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn dot(&self, other: Real3D) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: Real3D) -> Real3D {
+        Real3D {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    // The direction of the vector as a unit vector, (0, 0, 0) for a null vector.
+    pub fn unit(&self) -> Real3D {
+        let length = self.length();
+        if length == 0. {
+            Real3D::default()
+        } else {
+            *self * (1. / length)
+        }
+    }
+}
+
+impl Add for Real3D {
+    type Output = Real3D;
+
+    fn add(self, other: Real3D) -> Real3D {
+        Real3D::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Real3D {
+    type Output = Real3D;
+
+    fn sub(self, other: Real3D) -> Real3D {
+        Real3D::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f32> for Real3D {
+    type Output = Real3D;
+
+    fn mul(self, k: f32) -> Real3D {
+        Real3D::new(self.x * k, self.y * k, self.z * k)
+    }
+}
+
+impl fmt::Display for Real3D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+// Continuous 3D field, the counterpart of Field2D: objects are hashed in cells of side at most
+// `discretization`, and the locations set during a step are only visible after lazy_update.
+pub struct Field3D<O: Copy> {
+    pub dim: (f32, f32, f32),
+    pub toroidal: bool,
+    // Cells along each axis, and their size, so that they tile the field exactly
+    cells: [i32; 3],
+    size: [f32; 3],
+    read: HashMap<[i32; 3], Vec<O>>,
+    write: Mutex<HashMap<[i32; 3], Vec<O>>>,
+}
+
+impl<O: Copy> Field3D<O> {
+    pub fn new(dim: (f32, f32, f32), discretization: f32, toroidal: bool) -> Self {
+        let cells =
+            [dim.0, dim.1, dim.2].map(|side| ((side / discretization).ceil() as i32).max(1));
+        Field3D {
+            dim,
+            toroidal,
+            cells,
+            size: [
+                dim.0 / cells[0] as f32,
+                dim.1 / cells[1] as f32,
+                dim.2 / cells[2] as f32,
+            ],
+            read: HashMap::new(),
+            write: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cell(&self, loc: Real3D) -> [i32; 3] {
+        let values = [loc.x, loc.y, loc.z];
+        std::array::from_fn(|axis| {
+            ((values[axis] / self.size[axis]).floor() as i32).clamp(0, self.cells[axis] - 1)
+        })
+    }
+
+    pub fn set_object_location(&self, object: O, loc: Real3D) {
+        let cell = self.cell(loc);
+        self.write
+            .lock()
+            .unwrap()
+            .entry(cell)
+            .or_default()
+            .push(object);
+    }
+
+    // Make the locations set during the last step visible, and start a new step.
+    pub fn lazy_update(&mut self) {
+        self.read = std::mem::take(self.write.get_mut().unwrap());
+    }
+
+    // Objects in the cells within `distance` of `loc`, some of them farther than `distance`, as
+    // get_neighbors_within_relax_distance of Field2D.
+    pub fn get_neighbors_within_relax_distance(&self, loc: Real3D, distance: f32) -> Vec<O> {
+        let center = self.cell(loc);
+        let range = |axis: usize| -> Vec<i32> {
+            let (center, cells) = (center[axis], self.cells[axis]);
+            let radius = (distance / self.size[axis]).ceil() as i32;
+            if self.toroidal && 2 * radius + 1 >= cells {
+                // The range covers the whole axis, every cell once
+                (0..cells).collect()
+            } else if self.toroidal {
+                (center - radius..=center + radius)
+                    .map(|i| i.rem_euclid(cells))
+                    .collect()
+            } else {
+                ((center - radius).max(0)..=(center + radius).min(cells - 1)).collect()
+            }
+        };
+
+        let mut neighbours = Vec::new();
+        for i in range(0) {
+            for j in range(1) {
+                for k in range(2) {
+                    if let Some(cell) = self.read.get(&[i, j, k]) {
+                        neighbours.extend_from_slice(cell);
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    // Offset of `to` from `from`, across the edges of the field if it is toroidal.
+    pub fn offset(&self, from: Real3D, to: Real3D) -> Real3D {
+        if self.toroidal {
+            Real3D {
+                x: toroidal_distance(to.x, from.x, self.dim.0),
+                y: toroidal_distance(to.y, from.y, self.dim.1),
+                z: toroidal_distance(to.z, from.z, self.dim.2),
+            }
+        } else {
+            to - from
+        }
+    }
+}
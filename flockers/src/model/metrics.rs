@@ -0,0 +1,280 @@
+use crate::model::bird::Bird;
+use crate::model::state::Flocker;
+use krabmaga::engine::fields::field_2d::toroidal_distance;
+use krabmaga::engine::location::Real2D;
+use krabmaga::hashbrown::HashMap;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::fs::{self, File};
+use std::path::Path;
+
+// Collective measures of the flock after a step, one row of the output file. Step 0 is the
+// initial placement of the birds.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct FlockMetrics {
+    // Repetition of the simulation the step belongs to, starting from 1
+    pub run: u32,
+    pub step: u64,
+    // Birds flying, and birds caught by the predators since the beginning of the run
+    pub birds: u32,
+    pub caught: u32,
+    // Order parameters, between 0 and 1: the length of the mean heading of the birds (1 when they
+    // all fly in the same direction), and the mean angular momentum around the centre of the flock
+    // (1 when they all circle it in the same direction, as in a mill)
+    pub polarization: f64,
+    pub milling: f64,
+    // Distribution of the distances of the birds from their nearest neighbour, among the birds with
+    // a neighbour within nearest_neighbour_radius; the others are isolated
+    pub nnd_mean: f64,
+    pub nnd_p10: f64,
+    pub nnd_median: f64,
+    pub nnd_p90: f64,
+    pub isolated: u32,
+    // Flocks found by DBSCAN, and birds belonging to none of them
+    pub flocks: u32,
+    pub largest_flock: u32,
+    pub mean_flock_size: f64,
+    pub unflocked: u32,
+}
+
+// Flock metrics of the current run, written to a CSV file (if any) after every step.
+#[derive(Default)]
+pub struct Metrics {
+    pub run: u32,
+    writer: Option<csv::Writer<File>>,
+}
+
+impl Metrics {
+    #[allow(dead_code)]
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Metrics, String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+        }
+        let writer = csv::Writer::from_path(path)
+            .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+        Ok(Metrics {
+            run: 0,
+            writer: Some(writer),
+        })
+    }
+
+    // The metrics are only measured when they are written somewhere.
+    pub fn enabled(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    // Start a new repetition. The rows of all the repetitions go in the same file.
+    pub fn start_run(&mut self) {
+        self.run += 1;
+    }
+
+    // Write the metrics of a step. The file is closed on the first error, which stops the run.
+    pub fn record(&mut self, metrics: &FlockMetrics) -> Result<(), String> {
+        if let Some(writer) = self.writer.as_mut() {
+            // Flush every step: a run stopped early keeps all the rows written so far
+            let written = writer
+                .serialize(metrics)
+                .and_then(|_| writer.flush().map_err(csv::Error::from));
+            if let Err(e) = written {
+                self.writer = None;
+                return Err(format!("Unable to write the flock metrics: {}", e));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Measure the flock of `state.birds`, whose locations must match the read buffer of `field1`.
+pub fn measure(state: &Flocker, step: u64) -> FlockMetrics {
+    let birds = &state.birds;
+    let index: HashMap<u32, usize> = birds
+        .iter()
+        .enumerate()
+        .map(|(i, bird)| (bird.id, i))
+        .collect();
+    let flocks = dbscan(birds.len(), state.params.cluster_min_birds, |i| {
+        neighbours(state, &birds[i], state.params.cluster_distance)
+            .filter_map(|(other, _)| index.get(&other.id).copied())
+            .collect()
+    });
+
+    summarize(
+        state.metrics.run,
+        step,
+        birds.len(),
+        state.caught,
+        order_parameters(state, birds),
+        birds
+            .iter()
+            .filter_map(|bird| nearest_neighbour(state, bird))
+            .collect(),
+        flocks,
+    )
+}
+
+// Row of the metrics of `birds` birds, given their order parameters, the distances of the ones
+// that are not isolated from their nearest neighbour and the sizes of the flocks. Shared by the 2D
+// and 3D variants.
+pub fn summarize(
+    run: u32,
+    step: u64,
+    birds: usize,
+    caught: u32,
+    (polarization, milling): (f64, f64),
+    mut distances: Vec<f32>,
+    flocks: Vec<usize>,
+) -> FlockMetrics {
+    distances.sort_unstable_by(|a, b| a.total_cmp(b));
+    let quantile = |q: f64| {
+        if distances.is_empty() {
+            0.
+        } else {
+            distances[((distances.len() - 1) as f64 * q).round() as usize] as f64
+        }
+    };
+    let flocked: usize = flocks.iter().sum();
+
+    FlockMetrics {
+        run,
+        step,
+        birds: birds as u32,
+        caught,
+        polarization,
+        milling,
+        nnd_mean: mean(distances.iter().map(|d| *d as f64), distances.len()),
+        nnd_p10: quantile(0.1),
+        nnd_median: quantile(0.5),
+        nnd_p90: quantile(0.9),
+        isolated: (birds - distances.len()) as u32,
+        flocks: flocks.len() as u32,
+        largest_flock: flocks.iter().max().copied().unwrap_or(0) as u32,
+        mean_flock_size: mean(flocks.iter().map(|size| *size as f64), flocks.len()),
+        unflocked: (birds - flocked) as u32,
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>, count: usize) -> f64 {
+    if count == 0 {
+        0.
+    } else {
+        values.sum::<f64>() / count as f64
+    }
+}
+
+// Offset of `to` from `from`, across the edges of the field.
+fn offset(state: &Flocker, from: Real2D, to: Real2D) -> (f32, f32) {
+    (
+        toroidal_distance(to.x, from.x, state.dim.0),
+        toroidal_distance(to.y, from.y, state.dim.1),
+    )
+}
+
+// Polarization and milling of the birds. On a toroidal field the centre of the flock is the
+// circular mean of the locations, which does not jump when birds cross an edge.
+fn order_parameters(state: &Flocker, birds: &[Bird]) -> (f64, f64) {
+    if birds.is_empty() {
+        return (0., 0.);
+    }
+    let circular_mean = |values: &mut dyn Iterator<Item = f32>, dim: f32| {
+        let (mut sin, mut cos) = (0., 0.);
+        for value in values {
+            let angle = value / dim * 2. * PI;
+            sin += angle.sin();
+            cos += angle.cos();
+        }
+        (f32::atan2(sin, cos) / (2. * PI) * dim).rem_euclid(dim)
+    };
+    let center = Real2D {
+        x: circular_mean(&mut birds.iter().map(|bird| bird.loc.x), state.dim.0),
+        y: circular_mean(&mut birds.iter().map(|bird| bird.loc.y), state.dim.1),
+    };
+
+    let (mut heading_x, mut heading_y) = (0., 0.);
+    let mut momentum = 0.;
+    for bird in birds {
+        let speed = (bird.last_d.x * bird.last_d.x + bird.last_d.y * bird.last_d.y).sqrt();
+        if speed == 0. {
+            continue;
+        }
+        let (vx, vy) = (bird.last_d.x / speed, bird.last_d.y / speed);
+        heading_x += vx as f64;
+        heading_y += vy as f64;
+
+        let (rx, ry) = offset(state, center, bird.loc);
+        let radius = (rx * rx + ry * ry).sqrt();
+        if radius > 0. {
+            momentum += ((rx * vy - ry * vx) / radius) as f64;
+        }
+    }
+    let n = birds.len() as f64;
+    (
+        (heading_x * heading_x + heading_y * heading_y).sqrt() / n,
+        momentum.abs() / n,
+    )
+}
+
+// Birds of field1 within `distance` from `bird`, itself excluded.
+fn neighbours<'a>(
+    state: &'a Flocker,
+    bird: &'a Bird,
+    distance: f32,
+) -> impl Iterator<Item = (Bird, f32)> + 'a {
+    state
+        .field1
+        .get_neighbors_within_relax_distance(bird.loc, distance)
+        .into_iter()
+        .filter(move |other| other.id != bird.id && !state.caught_birds.contains(other))
+        .map(move |other| {
+            let (dx, dy) = offset(state, bird.loc, other.loc);
+            (other, (dx * dx + dy * dy).sqrt())
+        })
+        .filter(move |(_, dis)| *dis <= distance)
+}
+
+fn nearest_neighbour(state: &Flocker, bird: &Bird) -> Option<f32> {
+    neighbours(state, bird, state.params.nearest_neighbour_radius)
+        .map(|(_, dis)| dis)
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+// Sizes of the flocks found by DBSCAN among `count` birds, where `region(i)` gives the birds
+// within the cluster distance of the bird `i`: a bird with at least `min_birds` birds (itself
+// included) in its region is the core of a flock, which grows with the regions of its cores.
+pub fn dbscan(count: usize, min_birds: usize, region: impl Fn(usize) -> Vec<usize>) -> Vec<usize> {
+    let mut visited = vec![false; count];
+    let mut flocked = vec![false; count];
+    let mut flocks = Vec::new();
+    for i in 0..count {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+        let neighbours = region(i);
+        if neighbours.len() + 1 < min_birds {
+            continue;
+        }
+
+        flocked[i] = true;
+        let mut size = 1;
+        let mut queue = VecDeque::from(neighbours);
+        while let Some(j) = queue.pop_front() {
+            if !flocked[j] {
+                flocked[j] = true;
+                size += 1;
+            }
+            if visited[j] {
+                continue;
+            }
+            visited[j] = true;
+            let neighbours = region(j);
+            if neighbours.len() + 1 >= min_birds {
+                queue.extend(neighbours);
+            }
+        }
+        flocks.push(size);
+    }
+    flocks
+}
@@ -0,0 +1,95 @@
+use crate::model::bird_3d::Bird3D;
+use crate::model::field_3d::Real3D;
+use crate::model::metrics::{self, FlockMetrics};
+use crate::model::state_3d::Flocker3D;
+use krabmaga::hashbrown::HashMap;
+use std::f32::consts::PI;
+
+// Measure the flock of `state.birds` in 3D, with the same definitions as in 2D, so that the rows of
+// the two variants can be compared. The milling is the length of the mean angular momentum of the
+// birds around the centre of the flock, 1 when they all circle it around the same axis.
+pub fn measure(state: &Flocker3D, step: u64) -> FlockMetrics {
+    let birds = &state.birds;
+    let index: HashMap<u32, usize> = birds
+        .iter()
+        .enumerate()
+        .map(|(i, bird)| (bird.id, i))
+        .collect();
+    let flocks = metrics::dbscan(birds.len(), state.params.cluster_min_birds, |i| {
+        neighbours(state, &birds[i], state.params.cluster_distance)
+            .filter_map(|(other, _)| index.get(&other.id).copied())
+            .collect()
+    });
+
+    metrics::summarize(
+        state.metrics.run,
+        step,
+        birds.len(),
+        0,
+        order_parameters(state, birds),
+        birds
+            .iter()
+            .filter_map(|bird| {
+                neighbours(state, bird, state.params.nearest_neighbour_radius)
+                    .map(|(_, dis)| dis)
+                    .min_by(|a, b| a.total_cmp(b))
+            })
+            .collect(),
+        flocks,
+    )
+}
+
+// Polarization and milling of the birds. In a toroidal box the centre of the flock is the circular
+// mean of the locations, which does not jump when birds cross an edge, in a bounded one their mean.
+fn order_parameters(state: &Flocker3D, birds: &[Bird3D]) -> (f64, f64) {
+    if birds.is_empty() {
+        return (0., 0.);
+    }
+    let toroidal = state.params.toroidal;
+    let n = birds.len() as f64;
+    let mean = |values: &mut dyn Iterator<Item = f32>, dim: f32| {
+        if !toroidal {
+            return (values.map(|value| value as f64).sum::<f64>() / n) as f32;
+        }
+        let (mut sin, mut cos) = (0., 0.);
+        for value in values {
+            let angle = value / dim * 2. * PI;
+            sin += angle.sin();
+            cos += angle.cos();
+        }
+        (f32::atan2(sin, cos) / (2. * PI) * dim).rem_euclid(dim)
+    };
+    let center = Real3D {
+        x: mean(&mut birds.iter().map(|bird| bird.loc.x), state.dim.0),
+        y: mean(&mut birds.iter().map(|bird| bird.loc.y), state.dim.1),
+        z: mean(&mut birds.iter().map(|bird| bird.loc.z), state.dim.2),
+    };
+
+    let mut heading = Real3D::default();
+    let mut momentum = Real3D::default();
+    for bird in birds {
+        let v = bird.last_d.unit();
+        if v == Real3D::default() {
+            continue;
+        }
+        heading = heading + v;
+        let r = state.field1.offset(center, bird.loc).unit();
+        momentum = momentum + r.cross(v);
+    }
+    (heading.length() as f64 / n, momentum.length() as f64 / n)
+}
+
+// Birds of field1 within `distance` from `bird`, itself excluded.
+fn neighbours<'a>(
+    state: &'a Flocker3D,
+    bird: &'a Bird3D,
+    distance: f32,
+) -> impl Iterator<Item = (Bird3D, f32)> + 'a {
+    state
+        .field1
+        .get_neighbors_within_relax_distance(bird.loc, distance)
+        .into_iter()
+        .filter(move |other| other.id != bird.id)
+        .map(move |other| (other, state.field1.offset(bird.loc, other.loc).length()))
+        .filter(move |(_, dis)| *dis <= distance)
+}
@@ -1,2 +1,21 @@
 pub mod bird;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod bird_3d;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod exploration;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod field_3d;
+pub mod metrics;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod metrics_3d;
+pub mod obstacle;
+pub mod params;
+pub mod predator;
 pub mod state;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod state_3d;
+pub mod steering;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod steering_3d;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+pub mod trajectory;
@@ -0,0 +1,157 @@
+use krabmaga::engine::location::Real2D;
+
+// A static obstacle birds and predators steer around and never enter.
+#[derive(Clone)]
+pub enum Obstacle {
+    Circle { center: Real2D, radius: f32 },
+    // Vertices in order, either clockwise or counterclockwise
+    Polygon { vertices: Vec<Real2D> },
+}
+
+impl Obstacle {
+    pub fn contains(&self, loc: Real2D) -> bool {
+        match self {
+            Obstacle::Circle { center, radius } => {
+                let dx = loc.x - center.x;
+                let dy = loc.y - center.y;
+                dx * dx + dy * dy <= radius * radius
+            }
+            Obstacle::Polygon { vertices } => {
+                // Ray casting: a point is inside if a ray from it crosses the edges an odd number of times
+                let mut inside = false;
+                let mut j = vertices.len() - 1;
+                for i in 0..vertices.len() {
+                    let (a, b) = (vertices[i], vertices[j]);
+                    if (a.y > loc.y) != (b.y > loc.y)
+                        && loc.x < (b.x - a.x) * (loc.y - a.y) / (b.y - a.y) + a.x
+                    {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+
+    // The point of the border of the obstacle closest to `loc`.
+    pub fn closest_point(&self, loc: Real2D) -> Real2D {
+        match self {
+            Obstacle::Circle { center, radius } => {
+                let dx = loc.x - center.x;
+                let dy = loc.y - center.y;
+                let dis = (dx * dx + dy * dy).sqrt();
+                if dis == 0. {
+                    Real2D {
+                        x: center.x + radius,
+                        y: center.y,
+                    }
+                } else {
+                    Real2D {
+                        x: center.x + dx / dis * radius,
+                        y: center.y + dy / dis * radius,
+                    }
+                }
+            }
+            Obstacle::Polygon { vertices } => {
+                let mut closest = vertices[0];
+                let mut min = f32::INFINITY;
+                let mut j = vertices.len() - 1;
+                for i in 0..vertices.len() {
+                    let point = closest_on_segment(loc, vertices[j], vertices[i]);
+                    let dx = loc.x - point.x;
+                    let dy = loc.y - point.y;
+                    if dx * dx + dy * dy < min {
+                        min = dx * dx + dy * dy;
+                        closest = point;
+                    }
+                    j = i;
+                }
+                closest
+            }
+        }
+    }
+
+    // Push away from the obstacle for something on `loc`: a vector pointing out of the obstacle,
+    // from 0 at `range` from the border to 1 on the border and inside it.
+    pub fn avoidance(&self, loc: Real2D, range: f32) -> Real2D {
+        let closest = self.closest_point(loc);
+        let mut dx = loc.x - closest.x;
+        let mut dy = loc.y - closest.y;
+        let dis = (dx * dx + dy * dy).sqrt();
+        let inside = self.contains(loc);
+        if dis == 0. || (!inside && dis >= range) {
+            return Real2D { x: 0., y: 0. };
+        }
+        let strength = if inside {
+            // The border is ahead, the way out
+            dx = -dx;
+            dy = -dy;
+            1.
+        } else {
+            1. - dis / range
+        };
+        Real2D {
+            x: dx / dis * strength,
+            y: dy / dis * strength,
+        }
+    }
+}
+
+fn closest_on_segment(loc: Real2D, a: Real2D, b: Real2D) -> Real2D {
+    let (abx, aby) = (b.x - a.x, b.y - a.y);
+    let length = abx * abx + aby * aby;
+    if length == 0. {
+        return a;
+    }
+    let t = (((loc.x - a.x) * abx + (loc.y - a.y) * aby) / length).clamp(0., 1.);
+    Real2D {
+        x: a.x + t * abx,
+        y: a.y + t * aby,
+    }
+}
+
+// The obstacles of the field. Obstacles do not wrap around the edges of a toroidal field, so they
+// should be placed away from them.
+#[derive(Clone, Default)]
+pub struct Obstacles {
+    pub shapes: Vec<Obstacle>,
+}
+
+impl Obstacles {
+    // Two circles and a triangle, placed relative to the size of the field.
+    pub fn sample(dim: (f32, f32)) -> Self {
+        let (w, h) = dim;
+        let point = |x: f32, y: f32| Real2D { x: x * w, y: y * h };
+        Obstacles {
+            shapes: vec![
+                Obstacle::Circle {
+                    center: point(0.3, 0.65),
+                    radius: 0.1 * w.min(h),
+                },
+                Obstacle::Circle {
+                    center: point(0.7, 0.3),
+                    radius: 0.06 * w.min(h),
+                },
+                Obstacle::Polygon {
+                    vertices: vec![point(0.6, 0.65), point(0.8, 0.7), point(0.65, 0.85)],
+                },
+            ],
+        }
+    }
+
+    pub fn contains(&self, loc: Real2D) -> bool {
+        self.shapes.iter().any(|shape| shape.contains(loc))
+    }
+
+    // Sum of the pushes away from the obstacles within `range` from `loc`.
+    pub fn avoidance(&self, loc: Real2D, range: f32) -> Real2D {
+        let mut avoidance = Real2D { x: 0., y: 0. };
+        for shape in &self.shapes {
+            let push = shape.avoidance(loc, range);
+            avoidance.x += push.x;
+            avoidance.y += push.y;
+        }
+        avoidance
+    }
+}
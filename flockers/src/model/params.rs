@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Weights of the Boids rule and geometry of the fields. Values can be loaded from a TOML or JSON
+// file (`--config <file>`) and then overridden one by one from the command line
+// (`--<name> <value>`). Fields missing from the file keep their default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlockerParams {
+    // Weights of the terms of the Boids rule
+    pub cohesion: f32,
+    pub avoidance: f32,
+    pub randomness: f32,
+    pub consistency: f32,
+    pub momentum: f32,
+    // Distance flown by a bird at every step
+    pub jump: f32,
+    // Size of the cells of the fields, used to look up the neighbours
+    pub discretization: f32,
+    pub toroidal: bool,
+    // Distance within which birds see their neighbours with the Boids and Vicsek models
+    pub neighbour_radius: f32,
+    // Angular noise of the Vicsek model, in radians
+    pub vicsek_noise: f32,
+    // Couzin model: radii of the zones of repulsion, orientation and attraction, blind angle behind
+    // the birds and maximum turn per step, in radians, and angular noise
+    pub couzin_repulsion: f32,
+    pub couzin_orientation: f32,
+    pub couzin_attraction: f32,
+    pub couzin_blind_angle: f32,
+    pub couzin_turning_rate: f32,
+    pub couzin_noise: f32,
+    // Predators and obstacles, both disabled by default: number of predators, and whether the
+    // obstacles of Obstacles::sample are placed on the field
+    pub predators: u32,
+    pub obstacles: bool,
+    // Birds and predators closer than obstacle_range to an obstacle steer away from it with weight
+    // obstacle_avoidance
+    pub obstacle_avoidance: f32,
+    pub obstacle_range: f32,
+    // Birds flee from the predators closer than flee_radius with weight flee. Predators chase the
+    // nearest bird within predator_sight, slightly faster than the birds, and catch it within
+    // catch_distance
+    pub flee: f32,
+    pub flee_radius: f32,
+    pub predator_jump: f32,
+    pub predator_sight: f32,
+    pub catch_distance: f32,
+    // Flock metrics: nearest neighbours are looked for within nearest_neighbour_radius, and a flock
+    // is a DBSCAN cluster of birds with at least cluster_min_birds birds within cluster_distance of
+    // its cores
+    pub nearest_neighbour_radius: f32,
+    pub cluster_distance: f32,
+    pub cluster_min_birds: usize,
+}
+
+impl Default for FlockerParams {
+    fn default() -> Self {
+        FlockerParams {
+            cohesion: 0.8,
+            avoidance: 1.0,
+            randomness: 1.1,
+            consistency: 0.7,
+            momentum: 1.0,
+            jump: 0.7,
+            discretization: 10.0 / 1.5,
+            toroidal: true,
+            neighbour_radius: 10.0,
+            vicsek_noise: 0.5,
+            couzin_repulsion: 1.0,
+            couzin_orientation: 6.0,
+            couzin_attraction: 14.0,
+            couzin_blind_angle: std::f32::consts::FRAC_PI_2,
+            couzin_turning_rate: 0.7,
+            couzin_noise: 0.1,
+            predators: 0,
+            obstacles: false,
+            obstacle_avoidance: 4.0,
+            obstacle_range: 5.0,
+            flee: 2.0,
+            flee_radius: 15.0,
+            predator_jump: 0.9,
+            predator_sight: 20.0,
+            catch_distance: 1.0,
+            nearest_neighbour_radius: 10.0,
+            cluster_distance: 5.0,
+            cluster_min_birds: 4,
+        }
+    }
+}
+
+impl FlockerParams {
+    // Load the parameters from a file. Files ending in `.json` are parsed as JSON, anything else as TOML.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<FlockerParams, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+        } else {
+            toml::from_str(&content)
+                .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+        }
+    }
+
+    // Apply the command line arguments (program name excluded) to `self`. `--config <file>`
+    // replaces the current values with the ones of the file, and `--<name> <value>` (or
+    // `--<name>=<value>`) overrides a single parameter. The other options of the simulation, such
+    // as `--model`, are listed in `options` and left to the caller; any other name is an error.
+    pub fn with_args(self, args: &[String], options: &[&str]) -> Result<FlockerParams, String> {
+        let mut params = self;
+        let mut overrides: Vec<(String, String)> = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("Unexpected argument {}", arg))?;
+            // Every option of the simulation takes a value
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for --{}", name))?;
+                    (name.to_string(), value.clone())
+                }
+            };
+
+            if name == "config" {
+                params = FlockerParams::from_file(&value)?;
+            } else if !options.contains(&name.as_str()) {
+                overrides.push((name.replace('-', "_"), value));
+            }
+        }
+
+        if !overrides.is_empty() {
+            params = apply_overrides(params, overrides)?;
+        }
+        params.validate()?;
+        Ok(params)
+    }
+
+    // Reject the sizes the fields and the steering rules cannot work with.
+    pub fn validate(&self) -> Result<(), String> {
+        let sizes = [
+            ("jump", self.jump),
+            ("discretization", self.discretization),
+            ("neighbour_radius", self.neighbour_radius),
+            ("couzin_repulsion", self.couzin_repulsion),
+            ("couzin_orientation", self.couzin_orientation),
+            ("couzin_attraction", self.couzin_attraction),
+            ("nearest_neighbour_radius", self.nearest_neighbour_radius),
+            ("cluster_distance", self.cluster_distance),
+        ];
+        for (name, value) in sizes {
+            if value.is_nan() || value <= 0. {
+                return Err(format!(
+                    "Invalid value for --{}: {}, it must be positive",
+                    name, value
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Apply the `--<name> <value>` overrides to the parameters.
+fn apply_overrides(
+    params: FlockerParams,
+    overrides: Vec<(String, String)>,
+) -> Result<FlockerParams, String> {
+    let mut table =
+        toml::Table::try_from(&params).map_err(|e| format!("Invalid parameters: {}", e))?;
+    for (name, value) in overrides {
+        if !table.contains_key(&name) {
+            return Err(format!("Unknown option --{}", name));
+        }
+        table.insert(name, parse_value(&value));
+    }
+    table
+        .try_into()
+        .map_err(|e| format!("Invalid parameters: {}", e))
+}
+
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("v = {}", value))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_file_lists_the_defaults() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("params.toml");
+        assert_eq!(
+            FlockerParams::from_file(path).unwrap(),
+            FlockerParams::default()
+        );
+    }
+
+    fn rejected(args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        FlockerParams::default()
+            .with_args(&args, &["model"])
+            .unwrap_err()
+    }
+
+    #[test]
+    fn non_positive_sizes_are_rejected() {
+        for name in [
+            "jump",
+            "discretization",
+            "neighbour_radius",
+            "couzin_repulsion",
+            "couzin_orientation",
+            "couzin_attraction",
+            "nearest_neighbour_radius",
+            "cluster_distance",
+        ] {
+            for value in ["0", "-1"] {
+                let err = rejected(&[&format!("--{}", name), value]);
+                assert!(err.starts_with(&format!("Invalid value for --{}", name)));
+            }
+        }
+    }
+
+    #[test]
+    fn arguments_without_dashes_are_rejected() {
+        assert_eq!(
+            rejected(&["-cohesion", "2"]),
+            "Unexpected argument -cohesion"
+        );
+        assert_eq!(
+            rejected(&["--model", "vicsek", "2"]),
+            "Unexpected argument 2"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use core::fmt;
+use krabmaga::engine::agent::Agent;
+use krabmaga::engine::fields::field_2d::{toroidal_distance, toroidal_transform, Location2D};
+use krabmaga::engine::location::Real2D;
+use krabmaga::engine::state::State;
+use krabmaga::rand;
+use krabmaga::rand::Rng;
+use std::hash::{Hash, Hasher};
+
+use crate::model::state::{Catch, Flocker};
+
+// A predator chasing the nearest bird in sight. A bird within the catch distance is caught and
+// removed from the simulation.
+#[derive(Clone, Copy)]
+pub struct Predator {
+    pub id: u32,
+    pub loc: Real2D,
+    pub last_d: Real2D,
+}
+
+impl Predator {
+    pub fn new(id: u32, loc: Real2D, last_d: Real2D) -> Self {
+        Predator { id, loc, last_d }
+    }
+}
+
+impl Agent for Predator {
+    fn step(&mut self, state: &mut dyn State) {
+        let state = state.as_any().downcast_ref::<Flocker>().unwrap();
+        let params = &state.params;
+        let width = state.dim.0;
+        let height = state.dim.1;
+
+        // Nearest bird in sight, ignoring the ones already caught
+        let mut prey = None;
+        let mut min = params.predator_sight;
+        for bird in state
+            .field1
+            .get_neighbors_within_relax_distance(self.loc, params.predator_sight)
+        {
+            if state.caught_birds.contains(&bird) {
+                continue;
+            }
+            let dx = toroidal_distance(bird.loc.x, self.loc.x, width);
+            let dy = toroidal_distance(bird.loc.y, self.loc.y, height);
+            let dis = (dx * dx + dy * dy).sqrt();
+            if dis <= min {
+                min = dis;
+                prey = Some((bird, dx, dy));
+            }
+        }
+
+        let (mut dx, mut dy) = match prey {
+            // Straight to the prey, keeping some momentum
+            Some((_, x, y)) if min > 0. => (
+                self.last_d.x + x / min * params.predator_jump,
+                self.last_d.y + y / min * params.predator_jump,
+            ),
+            Some(_) => (self.last_d.x, self.last_d.y),
+            // No bird in sight, wander around
+            None => {
+                let mut rng = rand::rng();
+                let r1: f32 = rng.random();
+                let r2: f32 = rng.random();
+                (
+                    self.last_d.x + (r1 * 2.0 - 1.0) * 0.5,
+                    self.last_d.y + (r2 * 2.0 - 1.0) * 0.5,
+                )
+            }
+        };
+        let avoidance = state.obstacles.avoidance(self.loc, params.obstacle_range);
+        dx += params.obstacle_avoidance * avoidance.x;
+        dy += params.obstacle_avoidance * avoidance.y;
+
+        let dis = (dx * dx + dy * dy).sqrt();
+        if dis > 0.0 {
+            // Never overshoot the prey
+            let jump = if prey.is_some() {
+                params.predator_jump.min(min)
+            } else {
+                params.predator_jump
+            };
+            dx = dx / dis * jump;
+            dy = dy / dis * jump;
+        }
+        self.last_d = Real2D { x: dx, y: dy };
+
+        let loc = Real2D {
+            x: toroidal_transform(self.loc.x + dx, width),
+            y: toroidal_transform(self.loc.y + dy, height),
+        };
+        if !state.obstacles.contains(loc) {
+            self.loc = loc;
+        }
+        state.predators.set_object_location(*self, self.loc);
+
+        if let Some((bird, _, _)) = prey {
+            let dx = toroidal_distance(bird.loc.x, self.loc.x, width);
+            let dy = toroidal_distance(bird.loc.y, self.loc.y, height);
+            if (dx * dx + dy * dy).sqrt() <= params.catch_distance {
+                state.catches.lock().unwrap().push(Catch {
+                    predator: self.id,
+                    bird,
+                });
+            }
+        }
+    }
+}
+
+impl Hash for Predator {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.id.hash(state);
+    }
+}
+
+impl Eq for Predator {}
+
+impl PartialEq for Predator {
+    fn eq(&self, other: &Predator) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Location2D<Real2D> for Predator {
+    fn get_location(self) -> Real2D {
+        self.loc
+    }
+
+    fn set_location(&mut self, loc: Real2D) {
+        self.loc = loc;
+    }
+}
+
+impl fmt::Display for Predator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} loc {}", self.id, self.loc)
+    }
+}
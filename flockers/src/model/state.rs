@@ -1,58 +1,170 @@
 use crate::model::bird::Bird;
-use crate::{DISCRETIZATION, TOROIDAL};
+use crate::model::metrics::{self, Metrics};
+use crate::model::obstacle::Obstacles;
+use crate::model::params::FlockerParams;
+use crate::model::predator::Predator;
+use crate::model::steering::Steering;
+#[cfg(feature = "parallel")]
+use krabmaga::engine::agentimpl::AgentImpl;
 use krabmaga::engine::fields::field::Field;
 use krabmaga::engine::fields::field_2d::Field2D;
 use krabmaga::engine::location::Real2D;
 use krabmaga::engine::schedule::Schedule;
 use krabmaga::engine::state::State;
+use krabmaga::hashbrown::HashSet;
 use krabmaga::rand;
 use krabmaga::rand::Rng;
 use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+// A bird caught by a predator during a step, removed in after_step.
+#[derive(Clone, Copy)]
+pub struct Catch {
+    pub predator: u32,
+    pub bird: Bird,
+}
 
 pub struct Flocker {
     pub step: u64,
     pub field1: Field2D<Bird>,
+    pub predators: Field2D<Predator>,
     pub initial_flockers: u32,
+    pub params: FlockerParams,
+    // Rule the birds steer by
+    pub steering: Steering,
+    pub obstacles: Obstacles,
+    // Birds caught during the current step
+    pub catches: Mutex<Vec<Catch>>,
+    // Birds caught during the last step. They are still in the read buffer of field1 during the
+    // following step, birds and predators must ignore them.
+    pub caught_birds: HashSet<Bird>,
+    // Birds caught since the beginning of the run
+    pub caught: u32,
+    // Birds flying at the end of the last step, collected from the schedule
+    pub birds: Vec<Bird>,
+    pub metrics: Metrics,
+    // Error of the metrics file that stopped the run, shared with main to report it once the
+    // simulation is over
+    pub error: Arc<Mutex<Option<String>>>,
     pub dim: (f32, f32),
 }
 
 impl Flocker {
     #[allow(dead_code)]
-    pub fn new(dim: (f32, f32), initial_flockers: u32) -> Self {
+    pub fn new(dim: (f32, f32), initial_flockers: u32, params: FlockerParams) -> Self {
         Flocker {
             step: 0,
-            field1: Field2D::new(dim.0, dim.1, DISCRETIZATION, TOROIDAL),
+            field1: Field2D::new(dim.0, dim.1, params.discretization, params.toroidal),
+            predators: Field2D::new(dim.0, dim.1, params.discretization, params.toroidal),
             initial_flockers,
+            steering: Steering::Boids {
+                radius: params.neighbour_radius,
+            },
+            obstacles: if params.obstacles {
+                Obstacles::sample(dim)
+            } else {
+                Obstacles::default()
+            },
+            params,
+            catches: Mutex::new(Vec::new()),
+            caught_birds: HashSet::new(),
+            caught: 0,
+            birds: Vec::new(),
+            metrics: Metrics::default(),
+            error: Arc::new(Mutex::new(None)),
             dim,
         }
     }
+
+    // A random location of the field outside the obstacles.
+    fn free_location(&self, rng: &mut impl Rng) -> Real2D {
+        loop {
+            let r1: f32 = rng.random();
+            let r2: f32 = rng.random();
+            let loc = Real2D {
+                x: self.dim.0 * r1,
+                y: self.dim.1 * r2,
+            };
+            if !self.obstacles.contains(loc) {
+                return loc;
+            }
+        }
+    }
 }
 
 impl State for Flocker {
     fn reset(&mut self) {
         self.step = 0;
-        self.field1 = Field2D::new(self.dim.0, self.dim.1, DISCRETIZATION, TOROIDAL);
+        let (discretization, toroidal) = (self.params.discretization, self.params.toroidal);
+        self.field1 = Field2D::new(self.dim.0, self.dim.1, discretization, toroidal);
+        self.predators = Field2D::new(self.dim.0, self.dim.1, discretization, toroidal);
+        self.catches = Mutex::new(Vec::new());
+        self.caught_birds.clear();
+        self.caught = 0;
+        self.birds.clear();
     }
 
     fn init(&mut self, schedule: &mut Schedule) {
         let mut rng = rand::rng();
+        self.catches = Mutex::new(Vec::new());
+        self.caught_birds.clear();
+        self.caught = 0;
+        self.birds.clear();
+        self.metrics.start_run();
         // Should be moved in the init method on the model exploration changes
         for bird_id in 0..self.initial_flockers {
-            let r1: f32 = rng.random();
-            let r2: f32 = rng.random();
             let last_d = Real2D { x: 0., y: 0. };
-            let loc = Real2D {
-                x: self.dim.0 * r1,
-                y: self.dim.1 * r2,
-            };
+            let loc = self.free_location(&mut rng);
             let bird = Bird::new(bird_id, loc, last_d);
             self.field1.set_object_location(bird, loc);
             schedule.schedule_repeating(Box::new(bird), 0., 0);
+            self.birds.push(bird);
+        }
+
+        // Predators are scheduled after the birds, their ids follow the ones of the birds
+        for i in 0..self.params.predators {
+            let last_d = Real2D { x: 0., y: 0. };
+            let loc = self.free_location(&mut rng);
+            let predator = Predator::new(self.initial_flockers + i, loc, last_d);
+            self.predators.set_object_location(predator, loc);
+            schedule.schedule_repeating(Box::new(predator), 0., 1);
         }
     }
 
-    fn update(&mut self, _step: u64) {
+    fn update(&mut self, step: u64) {
         self.field1.lazy_update();
+        self.predators.lazy_update();
+        // field1 now holds the locations of the birds after the step
+        if self.metrics.enabled() {
+            let metrics = metrics::measure(self, step);
+            if let Err(err) = self.metrics.record(&metrics) {
+                self.error.lock().unwrap().get_or_insert(err);
+            }
+        }
+    }
+
+    fn end_condition(&mut self, _schedule: &mut Schedule) -> bool {
+        self.error.lock().unwrap().is_some()
+    }
+
+    fn after_step(&mut self, schedule: &mut Schedule) {
+        // A bird claimed by several predators is caught by the one with the lowest id. Birds are
+        // scheduled in the order of their ids, which are also their ids in the schedule.
+        let mut catches = std::mem::take(self.catches.get_mut().unwrap());
+        catches.sort_unstable_by_key(|catch| (catch.bird.id, catch.predator));
+        catches.dedup_by_key(|catch| catch.bird.id);
+        self.caught_birds.clear();
+        for catch in catches {
+            dequeue(schedule, catch.bird);
+            self.caught_birds.insert(catch.bird);
+            self.caught += 1;
+        }
+
+        self.birds = schedule
+            .get_all_events()
+            .iter()
+            .filter_map(|agent| agent.downcast_ref::<Bird>().copied())
+            .collect();
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -71,3 +183,67 @@ impl State for Flocker {
         self
     }
 }
+
+// Remove a caught bird from the schedule. The parallel schedule has no dequeue, its queue is
+// keyed by the id in the schedule as well.
+#[cfg(not(feature = "parallel"))]
+fn dequeue(schedule: &mut Schedule, bird: Bird) {
+    schedule.dequeue(Box::new(bird), bird.id);
+}
+
+#[cfg(feature = "parallel")]
+fn dequeue(schedule: &mut Schedule, bird: Bird) {
+    schedule
+        .events
+        .lock()
+        .unwrap()
+        .remove(&AgentImpl::new(Box::new(bird), bird.id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn predators(schedule: &Schedule) -> usize {
+        schedule
+            .get_all_events()
+            .iter()
+            .filter(|agent| agent.downcast_ref::<Predator>().is_some())
+            .count()
+    }
+
+    #[test]
+    fn predators_and_obstacles_are_enabled_by_the_parameters() {
+        let mut state = Flocker::new((100., 100.), 10, FlockerParams::default());
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        assert_eq!(predators(&schedule), 0);
+        assert!(state.obstacles.shapes.is_empty());
+
+        let args: Vec<String> = ["--predators", "3", "--obstacles", "true"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let params = FlockerParams::default().with_args(&args, &[]).unwrap();
+        let mut state = Flocker::new((100., 100.), 10, params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        assert_eq!(predators(&schedule), 3);
+        assert_eq!(state.obstacles.shapes.len(), 3);
+    }
+
+    // /dev/full accepts the file and fails every write
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unwritable_metrics_stop_the_run() {
+        let mut state = Flocker::new((20., 20.), 10, FlockerParams::default());
+        state.metrics = Metrics::create("/dev/full").unwrap();
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        assert!(!state.end_condition(&mut schedule));
+        schedule.step(&mut state);
+        assert!(state.end_condition(&mut schedule));
+        let error = state.error.lock().unwrap().clone().unwrap();
+        assert!(error.starts_with("Unable to write the flock metrics"));
+    }
+}
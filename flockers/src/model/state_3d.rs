@@ -0,0 +1,177 @@
+use crate::model::bird_3d::Bird3D;
+use crate::model::field_3d::{Field3D, Real3D};
+use crate::model::metrics::Metrics;
+use crate::model::metrics_3d;
+use crate::model::params::FlockerParams;
+use crate::model::steering::Steering;
+use crate::model::trajectory::Trajectory;
+use krabmaga::engine::schedule::Schedule;
+use krabmaga::engine::state::State;
+use krabmaga::rand;
+use krabmaga::rand::Rng;
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+// The 3D variant of Flocker, with the same parameters, steering rules and metrics. Birds fly
+// without predators nor obstacles.
+pub struct Flocker3D {
+    pub step: u64,
+    pub field1: Field3D<Bird3D>,
+    pub initial_flockers: u32,
+    pub params: FlockerParams,
+    // Rule the birds steer by
+    pub steering: Steering,
+    // Birds flying at the end of the last step, collected from the schedule
+    pub birds: Vec<Bird3D>,
+    pub metrics: Metrics,
+    pub trajectory: Trajectory,
+    // Error of an output file that stopped the run, shared with main to report it once the
+    // simulation is over
+    pub error: Arc<Mutex<Option<String>>>,
+    pub dim: (f32, f32, f32),
+}
+
+impl Flocker3D {
+    pub fn new(dim: (f32, f32, f32), initial_flockers: u32, params: FlockerParams) -> Self {
+        Flocker3D {
+            step: 0,
+            field1: Field3D::new(dim, params.discretization, params.toroidal),
+            initial_flockers,
+            steering: Steering::Boids {
+                radius: params.neighbour_radius,
+            },
+            params,
+            birds: Vec::new(),
+            metrics: Metrics::default(),
+            trajectory: Trajectory::default(),
+            error: Arc::new(Mutex::new(None)),
+            dim,
+        }
+    }
+
+    // Stop the run with an error, unless it has already been stopped by another one.
+    fn fail(&self, err: String) {
+        self.error.lock().unwrap().get_or_insert(err);
+    }
+}
+
+impl State for Flocker3D {
+    fn reset(&mut self) {
+        self.step = 0;
+        self.field1 = Field3D::new(self.dim, self.params.discretization, self.params.toroidal);
+        self.birds.clear();
+    }
+
+    fn init(&mut self, schedule: &mut Schedule) {
+        let mut rng = rand::rng();
+        self.birds.clear();
+        self.metrics.start_run();
+        for bird_id in 0..self.initial_flockers {
+            let last_d = Real3D::default();
+            let loc = Real3D {
+                x: self.dim.0 * rng.random::<f32>(),
+                y: self.dim.1 * rng.random::<f32>(),
+                z: self.dim.2 * rng.random::<f32>(),
+            };
+            let bird = Bird3D::new(bird_id, loc, last_d);
+            self.field1.set_object_location(bird, loc);
+            schedule.schedule_repeating(Box::new(bird), 0., 0);
+            self.birds.push(bird);
+        }
+    }
+
+    fn update(&mut self, step: u64) {
+        self.field1.lazy_update();
+        // field1 now holds the locations of the birds after the step
+        if self.metrics.enabled() {
+            let metrics = metrics_3d::measure(self, step);
+            if let Err(err) = self.metrics.record(&metrics) {
+                self.fail(err);
+            }
+        }
+        let recorded = self
+            .trajectory
+            .record(self.metrics.run, step, &self.birds, self.dim);
+        if let Err(err) = recorded {
+            self.fail(err);
+        }
+    }
+
+    fn end_condition(&mut self, _schedule: &mut Schedule) -> bool {
+        self.error.lock().unwrap().is_some()
+    }
+
+    fn after_step(&mut self, schedule: &mut Schedule) {
+        self.birds = schedule
+            .get_all_events()
+            .iter()
+            .filter_map(|agent| agent.downcast_ref::<Bird3D>().copied())
+            .collect();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_state_mut(&mut self) -> &mut dyn State {
+        self
+    }
+
+    fn as_state(&self) -> &dyn State {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // /dev/full accepts the file and fails every write
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unwritable_trajectory_stops_the_run() {
+        let mut state = Flocker3D::new((10., 10., 10.), 10, FlockerParams::default());
+        state.trajectory = Trajectory::create("/dev/full").unwrap();
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        assert!(!state.end_condition(&mut schedule));
+        schedule.step(&mut state);
+        assert!(state.end_condition(&mut schedule));
+        let error = state.error.lock().unwrap().clone().unwrap();
+        assert!(error.starts_with("Unable to write the trajectory"));
+    }
+
+    // In a bounded box birds bounce off the walls instead of reappearing on the opposite side, so
+    // they never move farther than a jump in a step.
+    #[test]
+    fn birds_bounce_off_the_walls_of_a_bounded_box() {
+        let params = FlockerParams {
+            toroidal: false,
+            ..FlockerParams::default()
+        };
+        let jump = params.jump;
+        let dim = (4., 4., 4.);
+        let mut state = Flocker3D::new(dim, 20, params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        for _ in 0..100 {
+            let before = state.birds.clone();
+            schedule.step(&mut state);
+            for bird in state.birds.iter() {
+                let last = before.iter().find(|last| last.id == bird.id).unwrap();
+                assert!((bird.loc - last.loc).length() <= jump * 1.001);
+                for (value, side) in [
+                    (bird.loc.x, dim.0),
+                    (bird.loc.y, dim.1),
+                    (bird.loc.z, dim.2),
+                ] {
+                    assert!((0. ..=side).contains(&value));
+                }
+            }
+        }
+    }
+}
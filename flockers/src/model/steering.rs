@@ -0,0 +1,266 @@
+use crate::model::bird::Bird;
+use crate::model::params::FlockerParams;
+use krabmaga::engine::location::Real2D;
+use krabmaga::rand::Rng;
+use std::f32::consts::PI;
+
+// Scale of the terms of the Boids rule, before their weights: avoidance grows fast as neighbours
+// get closer, cohesion is a tenth of the offset from the neighbours, randomness a short step.
+pub const BOIDS_AVOIDANCE_SCALE: f32 = 400.0;
+pub const BOIDS_COHESION_SCALE: f32 = 0.1;
+pub const BOIDS_RANDOMNESS_SCALE: f32 = 0.05;
+
+// A bird seen by another one, with its offset from the bird looking at it, across the edges of
+// the field (pointing from the neighbour to the bird).
+#[derive(Clone, Copy)]
+pub struct Neighbour {
+    pub bird: Bird,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+impl Neighbour {
+    pub fn distance(&self) -> f32 {
+        (self.dx * self.dx + self.dy * self.dy).sqrt()
+    }
+}
+
+// Rule a bird uses to choose its direction from its neighbours. Fleeing the predators and avoiding
+// the obstacles are added to the direction by the bird, whatever the rule.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Steering {
+    // Reynolds' Boids: weighted sum of avoidance, cohesion, consistency (alignment), randomness and
+    // momentum, weighted by the FlockerParams of the same names.
+    Boids {
+        radius: f32,
+    },
+    // Vicsek model: the mean heading of the neighbours within `radius` (the bird included), turned
+    // by a uniform random angle in [-noise / 2, noise / 2].
+    Vicsek {
+        radius: f32,
+        noise: f32,
+    },
+    // Couzin zone model. Neighbours closer than `repulsion` are avoided first; otherwise the bird
+    // aligns with the ones closer than `orientation` and moves towards the ones closer than
+    // `attraction`. Neighbours within the `blind_angle` behind the bird are not seen, the bird
+    // turns at most `turning_rate` radians per step, and the direction is turned by a uniform
+    // random angle in [-noise / 2, noise / 2].
+    Couzin {
+        repulsion: f32,
+        orientation: f32,
+        attraction: f32,
+        blind_angle: f32,
+        turning_rate: f32,
+        noise: f32,
+    },
+}
+
+impl Steering {
+    // Distance within which a bird looks for its neighbours.
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Steering::Boids { radius } | Steering::Vicsek { radius, .. } => radius,
+            Steering::Couzin { attraction, .. } => attraction,
+        }
+    }
+
+    // Direction of the next move of `bird`, not normalized, given its neighbours within radius().
+    pub fn direction<R: Rng>(
+        &self,
+        bird: &Bird,
+        neighbours: &[Neighbour],
+        params: &FlockerParams,
+        rng: &mut R,
+    ) -> Real2D {
+        match *self {
+            Steering::Boids { .. } => boids(bird, neighbours, params, rng),
+            Steering::Vicsek { radius, noise } => {
+                let (mut x, mut y) = unit(bird.last_d);
+                for neighbour in neighbours.iter().filter(|n| n.distance() <= radius) {
+                    let (vx, vy) = unit(neighbour.bird.last_d);
+                    x += vx;
+                    y += vy;
+                }
+                // Without a heading around, e.g. at the beginning, a random one
+                let angle = if x == 0. && y == 0. {
+                    rng.random_range(-PI..PI)
+                } else {
+                    y.atan2(x)
+                };
+                from_angle(angle + noise * (rng.random::<f32>() - 0.5))
+            }
+            Steering::Couzin {
+                repulsion,
+                orientation,
+                attraction,
+                blind_angle,
+                turning_rate,
+                noise,
+            } => {
+                let heading = unit(bird.last_d);
+                let (mut rx, mut ry, mut repulsed) = (0., 0., false);
+                let (mut ox, mut oy, mut oriented) = (heading.0, heading.1, false);
+                let (mut ax, mut ay, mut attracted) = (0., 0., false);
+                for neighbour in neighbours {
+                    let dis = neighbour.distance();
+                    if dis == 0. || dis >= attraction {
+                        continue;
+                    }
+                    // Towards the neighbour
+                    let (tx, ty) = (-neighbour.dx / dis, -neighbour.dy / dis);
+                    if heading != (0., 0.)
+                        && (heading.0 * tx + heading.1 * ty).clamp(-1., 1.).acos()
+                            > PI - blind_angle / 2.
+                    {
+                        continue;
+                    }
+                    if dis < repulsion {
+                        rx -= tx;
+                        ry -= ty;
+                        repulsed = true;
+                    } else if dis < orientation {
+                        let (vx, vy) = unit(neighbour.bird.last_d);
+                        ox += vx;
+                        oy += vy;
+                        oriented = true;
+                    } else {
+                        ax += tx;
+                        ay += ty;
+                        attracted = true;
+                    }
+                }
+
+                let desired = if repulsed {
+                    unit(Real2D { x: rx, y: ry })
+                } else {
+                    let (ox, oy) = unit(Real2D { x: ox, y: oy });
+                    let (ax, ay) = unit(Real2D { x: ax, y: ay });
+                    match (oriented, attracted) {
+                        (true, true) => unit(Real2D {
+                            x: ox + ax,
+                            y: oy + ay,
+                        }),
+                        (true, false) => (ox, oy),
+                        (false, true) => (ax, ay),
+                        (false, false) => heading,
+                    }
+                };
+                let angle = if desired == (0., 0.) {
+                    rng.random_range(-PI..PI)
+                } else if heading == (0., 0.) {
+                    desired.1.atan2(desired.0)
+                } else {
+                    // Turn towards the desired direction, at most turning_rate
+                    let current = heading.1.atan2(heading.0);
+                    let turn = (desired.1.atan2(desired.0) - current + PI).rem_euclid(2. * PI) - PI;
+                    current + turn.clamp(-turning_rate, turning_rate)
+                };
+                from_angle(angle + noise * (rng.random::<f32>() - 0.5))
+            }
+        }
+    }
+}
+
+// The direction of `v` as a unit vector, (0, 0) for a null vector.
+fn unit(v: Real2D) -> (f32, f32) {
+    let length = (v.x * v.x + v.y * v.y).sqrt();
+    if length == 0. {
+        (0., 0.)
+    } else {
+        (v.x / length, v.y / length)
+    }
+}
+
+fn from_angle(angle: f32) -> Real2D {
+    Real2D {
+        x: angle.cos(),
+        y: angle.sin(),
+    }
+}
+
+fn boids<R: Rng>(
+    bird: &Bird,
+    neighbours: &[Neighbour],
+    params: &FlockerParams,
+    rng: &mut R,
+) -> Real2D {
+    let mut avoidance = Real2D { x: 0.0, y: 0.0 };
+    let mut cohesion = Real2D { x: 0.0, y: 0.0 };
+    let mut consistency = Real2D { x: 0.0, y: 0.0 };
+
+    if !neighbours.is_empty() {
+        let mut x_avoid = 0.0;
+        let mut y_avoid = 0.0;
+        let mut x_cohe = 0.0;
+        let mut y_cohe = 0.0;
+        let mut x_cons = 0.0;
+        let mut y_cons = 0.0;
+
+        for neighbour in neighbours {
+            let (dx, dy) = (neighbour.dx, neighbour.dy);
+
+            //avoidance calculation
+            let square = dx * dx + dy * dy;
+            x_avoid += dx / (square * square + 1.0);
+            y_avoid += dy / (square * square + 1.0);
+
+            //cohesion calculation
+            x_cohe += dx;
+            y_cohe += dy;
+
+            //consistency calculation
+            x_cons += neighbour.bird.last_d.x;
+            y_cons += neighbour.bird.last_d.y;
+        }
+
+        let count = neighbours.len() as f32;
+        x_avoid /= count;
+        y_avoid /= count;
+        x_cohe /= count;
+        y_cohe /= count;
+        x_cons /= count;
+        y_cons /= count;
+
+        consistency = Real2D {
+            x: x_cons / count,
+            y: y_cons / count,
+        };
+
+        avoidance = Real2D {
+            x: BOIDS_AVOIDANCE_SCALE * x_avoid,
+            y: BOIDS_AVOIDANCE_SCALE * y_avoid,
+        };
+
+        cohesion = Real2D {
+            x: -x_cohe * BOIDS_COHESION_SCALE,
+            y: -y_cohe * BOIDS_COHESION_SCALE,
+        };
+    }
+
+    //randomness
+    let r1: f32 = rng.random();
+    let x_rand = r1 * 2.0 - 1.0;
+    let r2: f32 = rng.random();
+    let y_rand = r2 * 2.0 - 1.0;
+
+    let square = (x_rand * x_rand + y_rand * y_rand).sqrt();
+    let randomness = Real2D {
+        x: BOIDS_RANDOMNESS_SCALE * x_rand / square,
+        y: BOIDS_RANDOMNESS_SCALE * y_rand / square,
+    };
+
+    let mom = bird.last_d;
+
+    Real2D {
+        x: params.cohesion * cohesion.x
+            + params.avoidance * avoidance.x
+            + params.consistency * consistency.x
+            + params.randomness * randomness.x
+            + params.momentum * mom.x,
+        y: params.cohesion * cohesion.y
+            + params.avoidance * avoidance.y
+            + params.consistency * consistency.y
+            + params.randomness * randomness.y
+            + params.momentum * mom.y,
+    }
+}
@@ -0,0 +1,204 @@
+use crate::model::bird_3d::Bird3D;
+use crate::model::field_3d::Real3D;
+use crate::model::params::FlockerParams;
+use crate::model::steering::{
+    Steering, BOIDS_AVOIDANCE_SCALE, BOIDS_COHESION_SCALE, BOIDS_RANDOMNESS_SCALE,
+};
+use krabmaga::rand::Rng;
+use std::f32::consts::PI;
+
+// A bird seen by another one in 3D, with its offset pointing from the neighbour to the bird, as
+// Neighbour.
+#[derive(Clone, Copy)]
+pub struct Neighbour3D {
+    pub bird: Bird3D,
+    pub offset: Real3D,
+}
+
+impl Neighbour3D {
+    pub fn distance(&self) -> f32 {
+        self.offset.length()
+    }
+}
+
+// The steering rules generalised to 3D. Angles become angles between vectors: the random turn of
+// Vicsek and Couzin deviates the direction by an angle in [0, noise / 2] around a random axis,
+// and Couzin turns at most `turning_rate` radians towards the desired direction.
+impl Steering {
+    // Direction of the next move of `bird`, not normalized, given its neighbours within radius().
+    pub fn direction_3d<R: Rng>(
+        &self,
+        bird: &Bird3D,
+        neighbours: &[Neighbour3D],
+        params: &FlockerParams,
+        rng: &mut R,
+    ) -> Real3D {
+        match *self {
+            Steering::Boids { .. } => boids(bird, neighbours, params, rng),
+            Steering::Vicsek { radius, noise } => {
+                let mut heading = bird.last_d.unit();
+                for neighbour in neighbours.iter().filter(|n| n.distance() <= radius) {
+                    heading = heading + neighbour.bird.last_d.unit();
+                }
+                // Without a heading around, e.g. at the beginning, a random one
+                let heading = if heading == Real3D::default() {
+                    random_unit(rng)
+                } else {
+                    heading.unit()
+                };
+                deviate(heading, noise, rng)
+            }
+            Steering::Couzin {
+                repulsion,
+                orientation,
+                attraction,
+                blind_angle,
+                turning_rate,
+                noise,
+            } => {
+                let heading = bird.last_d.unit();
+                let (mut repulse, mut repulsed) = (Real3D::default(), false);
+                let (mut orient, mut oriented) = (heading, false);
+                let (mut attract, mut attracted) = (Real3D::default(), false);
+                for neighbour in neighbours {
+                    let dis = neighbour.distance();
+                    if dis == 0. || dis >= attraction {
+                        continue;
+                    }
+                    // Towards the neighbour
+                    let towards = neighbour.offset * (-1. / dis);
+                    if heading != Real3D::default()
+                        && heading.dot(towards).clamp(-1., 1.).acos() > PI - blind_angle / 2.
+                    {
+                        continue;
+                    }
+                    if dis < repulsion {
+                        repulse = repulse - towards;
+                        repulsed = true;
+                    } else if dis < orientation {
+                        orient = orient + neighbour.bird.last_d.unit();
+                        oriented = true;
+                    } else {
+                        attract = attract + towards;
+                        attracted = true;
+                    }
+                }
+
+                let desired = if repulsed {
+                    repulse.unit()
+                } else {
+                    match (oriented, attracted) {
+                        (true, true) => (orient.unit() + attract.unit()).unit(),
+                        (true, false) => orient.unit(),
+                        (false, true) => attract.unit(),
+                        (false, false) => heading,
+                    }
+                };
+                let direction = if desired == Real3D::default() {
+                    random_unit(rng)
+                } else if heading == Real3D::default() {
+                    desired
+                } else {
+                    turn(heading, desired, turning_rate)
+                };
+                deviate(direction, noise, rng)
+            }
+        }
+    }
+}
+
+// A unit vector perpendicular to the unit vector `v`.
+fn perpendicular(v: Real3D) -> Real3D {
+    // Cross with the axis the least aligned with v
+    let axis = if v.x.abs() < 0.5 {
+        Real3D::new(1., 0., 0.)
+    } else {
+        Real3D::new(0., 1., 0.)
+    };
+    v.cross(axis).unit()
+}
+
+// A random direction, uniform on the sphere.
+fn random_unit<R: Rng>(rng: &mut R) -> Real3D {
+    let z: f32 = rng.random_range(-1.0..1.0);
+    let angle: f32 = rng.random_range(-PI..PI);
+    let r = (1. - z * z).sqrt();
+    Real3D::new(r * angle.cos(), r * angle.sin(), z)
+}
+
+// The unit vector `heading` deviated by a uniform random angle in [0, noise / 2], around a uniform
+// random axis perpendicular to it.
+fn deviate<R: Rng>(heading: Real3D, noise: f32, rng: &mut R) -> Real3D {
+    let angle = noise / 2. * rng.random::<f32>();
+    let azimuth: f32 = rng.random_range(-PI..PI);
+    let u = perpendicular(heading);
+    let v = heading.cross(u);
+    heading * angle.cos() + (u * azimuth.cos() + v * azimuth.sin()) * angle.sin()
+}
+
+// The unit vector `heading` turned towards the unit vector `desired`, at most `max_angle` radians.
+fn turn(heading: Real3D, desired: Real3D, max_angle: f32) -> Real3D {
+    let cos = heading.dot(desired).clamp(-1., 1.);
+    if cos.acos() <= max_angle {
+        return desired;
+    }
+    // Direction of the turn, in the plane of the two vectors
+    let side = (desired - heading * cos).unit();
+    let side = if side == Real3D::default() {
+        perpendicular(heading)
+    } else {
+        side
+    };
+    heading * max_angle.cos() + side * max_angle.sin()
+}
+
+fn boids<R: Rng>(
+    bird: &Bird3D,
+    neighbours: &[Neighbour3D],
+    params: &FlockerParams,
+    rng: &mut R,
+) -> Real3D {
+    let mut avoidance = Real3D::default();
+    let mut cohesion = Real3D::default();
+    let mut consistency = Real3D::default();
+
+    if !neighbours.is_empty() {
+        let mut avoid = Real3D::default();
+        let mut cohe = Real3D::default();
+        let mut cons = Real3D::default();
+
+        for neighbour in neighbours {
+            let offset = neighbour.offset;
+
+            //avoidance calculation
+            let square = offset.dot(offset);
+            avoid = avoid + offset * (1. / (square * square + 1.0));
+
+            //cohesion calculation
+            cohe = cohe + offset;
+
+            //consistency calculation
+            cons = cons + neighbour.bird.last_d;
+        }
+
+        // As in 2D, the consistency is divided twice by the number of neighbours
+        let count = neighbours.len() as f32;
+        consistency = cons * (1. / (count * count));
+        avoidance = avoid * (BOIDS_AVOIDANCE_SCALE / count);
+        cohesion = cohe * (-BOIDS_COHESION_SCALE / count);
+    }
+
+    //randomness
+    let random = Real3D::new(
+        rng.random::<f32>() * 2.0 - 1.0,
+        rng.random::<f32>() * 2.0 - 1.0,
+        rng.random::<f32>() * 2.0 - 1.0,
+    );
+    let randomness = random.unit() * BOIDS_RANDOMNESS_SCALE;
+
+    cohesion * params.cohesion
+        + avoidance * params.avoidance
+        + consistency * params.consistency
+        + randomness * params.randomness
+        + bird.last_d * params.momentum
+}
@@ -0,0 +1,125 @@
+use crate::model::bird_3d::Bird3D;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Location and heading of a bird after a step, one row of a CSV trajectory.
+#[derive(Serialize)]
+struct Position {
+    run: u32,
+    step: u64,
+    id: u32,
+    x: f32,
+    y: f32,
+    z: f32,
+    vx: f32,
+    vy: f32,
+    vz: f32,
+}
+
+enum Writer {
+    Csv(Box<csv::Writer<File>>),
+    Xyz(BufWriter<File>),
+}
+
+// Locations of the birds of the 3D variant after every step, for external rendering. Files ending
+// in `.xyz` are written in the extended XYZ format, one frame per step with the birds sorted by id,
+// readable by OVITO or VMD; anything else is a CSV file with one row per bird and step.
+#[derive(Default)]
+pub struct Trajectory {
+    writer: Option<Writer>,
+}
+
+impl Trajectory {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Trajectory, String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+        }
+        let is_xyz = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("xyz"));
+        let writer = if is_xyz {
+            File::create(path).map(|file| Writer::Xyz(BufWriter::new(file)))
+        } else {
+            csv::Writer::from_path(path)
+                .map(|writer| Writer::Csv(Box::new(writer)))
+                .map_err(|e| e.into())
+        };
+        Ok(Trajectory {
+            writer: Some(
+                writer.map_err(|e| format!("Unable to create {}: {}", path.display(), e))?,
+            ),
+        })
+    }
+
+    // The locations of the birds after a step of the repetition `run` of a field of size `dim`.
+    // The file is closed on the first error, which stops the run.
+    pub fn record(
+        &mut self,
+        run: u32,
+        step: u64,
+        birds: &[Bird3D],
+        dim: (f32, f32, f32),
+    ) -> Result<(), String> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        let mut birds = birds.to_vec();
+        birds.sort_unstable_by_key(|bird| bird.id);
+
+        // Flush every step: a run stopped early keeps all the steps written so far
+        let written = match writer {
+            Writer::Csv(writer) => birds
+                .iter()
+                .try_for_each(|bird| {
+                    writer.serialize(Position {
+                        run,
+                        step,
+                        id: bird.id,
+                        x: bird.loc.x,
+                        y: bird.loc.y,
+                        z: bird.loc.z,
+                        vx: bird.last_d.x,
+                        vy: bird.last_d.y,
+                        vz: bird.last_d.z,
+                    })
+                })
+                .and_then(|_| writer.flush().map_err(csv::Error::from))
+                .map_err(|e| e.to_string()),
+            Writer::Xyz(writer) => xyz_frame(writer, run, step, &birds, dim)
+                .and_then(|_| writer.flush())
+                .map_err(|e| e.to_string()),
+        };
+        if let Err(e) = written {
+            self.writer = None;
+            return Err(format!("Unable to write the trajectory: {}", e));
+        }
+        Ok(())
+    }
+}
+
+fn xyz_frame(
+    writer: &mut impl Write,
+    run: u32,
+    step: u64,
+    birds: &[Bird3D],
+    dim: (f32, f32, f32),
+) -> std::io::Result<()> {
+    writeln!(writer, "{}", birds.len())?;
+    writeln!(
+        writer,
+        "Lattice=\"{} 0 0 0 {} 0 0 0 {}\" Properties=species:S:1:pos:R:3:velo:R:3 Time={} run={}",
+        dim.0, dim.1, dim.2, step, run
+    )?;
+    for bird in birds {
+        writeln!(
+            writer,
+            "B {} {} {} {} {} {}",
+            bird.loc.x, bird.loc.y, bird.loc.z, bird.last_d.x, bird.last_d.y, bird.last_d.z
+        )?;
+    }
+    Ok(())
+}
@@ -1,2 +1,4 @@
 pub mod bird_vis;
+pub mod obstacle_vis;
+pub mod predator_vis;
 pub mod vis_state;
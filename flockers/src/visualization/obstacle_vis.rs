@@ -0,0 +1,47 @@
+use crate::model::obstacle::Obstacles;
+use crate::model::state::Flocker;
+use krabmaga::bevy::prelude::Image;
+use krabmaga::engine::location::{Int2D, Real2D};
+use krabmaga::visualization::fields::number_grid_2d::BatchRender;
+
+// The obstacles drawn in a texture with a pixel for each unit of the field. Obstacles never move,
+// so the texture is drawn once.
+pub struct ObstacleMap {
+    pub obstacles: Obstacles,
+    pub dim: (f32, f32),
+}
+
+impl ObstacleMap {
+    pub fn new(state: &Flocker) -> Self {
+        ObstacleMap {
+            obstacles: state.obstacles.clone(),
+            dim: state.dim,
+        }
+    }
+}
+
+impl BatchRender<Flocker> for ObstacleMap {
+    fn get_pixel(&self, loc: &Int2D) -> [u8; 4] {
+        let center = Real2D {
+            x: loc.x as f32 + 0.5,
+            y: loc.y as f32 + 0.5,
+        };
+        if self.obstacles.contains(center) {
+            [128u8, 128u8, 128u8, 255u8]
+        } else {
+            [0u8, 0u8, 0u8, 0u8]
+        }
+    }
+
+    fn get_dimensions(&self) -> (u32, u32) {
+        (self.dim.0 as u32, self.dim.1 as u32)
+    }
+
+    fn get_layer(&self) -> f32 {
+        0.
+    }
+
+    fn get_texture_from_state(state: &Flocker) -> Image {
+        ObstacleMap::new(state).texture()
+    }
+}
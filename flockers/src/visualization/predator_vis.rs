@@ -0,0 +1,60 @@
+use crate::model::predator::Predator;
+use crate::model::state::Flocker;
+use krabmaga::bevy::ecs as bevy_ecs;
+use krabmaga::bevy::prelude::{Component, Quat, Transform, Visibility};
+use krabmaga::engine::agent::Agent;
+use krabmaga::engine::state::State;
+use krabmaga::visualization::agent_render::{AgentRender, SpriteType};
+
+#[derive(Component)]
+pub struct PredatorVis {
+    pub(crate) id: u32,
+}
+
+impl AgentRender for PredatorVis {
+    fn sprite(&self, _agent: &Box<dyn Agent>, _state: &Box<&dyn State>) -> SpriteType {
+        SpriteType::Emoji(String::from("red_circle"))
+    }
+
+    fn location(&self, agent: &Box<dyn Agent>, state: &Box<&dyn State>) -> (f32, f32, f32) {
+        let state = state.as_any().downcast_ref::<Flocker>().unwrap();
+        let agent = agent.downcast_ref::<Predator>().unwrap();
+        let loc = state.predators.get_location(*agent);
+        match loc {
+            Some(loc) => (loc.x, loc.y, 1.),
+            None => (agent.loc.x, agent.loc.y, 1.),
+        }
+    }
+
+    fn scale(&self, _agent: &Box<dyn Agent>, _state: &Box<&dyn State>) -> (f32, f32) {
+        (0.05, 0.05)
+    }
+
+    fn rotation(&self, _agent: &Box<dyn Agent>, _state: &Box<&dyn State>) -> f32 {
+        0.
+    }
+
+    fn update(
+        &mut self,
+        agent: &Box<dyn Agent>,
+        transform: &mut Transform,
+        state: &Box<&dyn State>,
+        _visible: &mut Visibility,
+    ) {
+        let (loc_x, loc_y, z) = self.location(agent, state);
+        let rotation = self.rotation(agent, state);
+        let (scale_x, scale_y) = self.scale(agent, state);
+
+        let translation = &mut transform.translation;
+        translation.x = loc_x;
+        translation.y = loc_y;
+        translation.z = z;
+        transform.scale.x = scale_x;
+        transform.scale.y = scale_y;
+        transform.rotation = Quat::from_rotation_z(rotation);
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
@@ -5,12 +5,16 @@ use krabmaga::engine::schedule::Schedule;
 use krabmaga::engine::state::State;
 use krabmaga::visualization::agent_render::AgentRender;
 use krabmaga::visualization::asset_handle_factory::AssetHandleFactoryResource;
+use krabmaga::visualization::fields::number_grid_2d::BatchRender;
 use krabmaga::visualization::simulation_descriptor::SimulationDescriptor;
 use krabmaga::visualization::visualization_state::VisualizationState;
 
 use crate::model::bird::Bird;
+use crate::model::predator::Predator;
 use crate::model::state::Flocker;
 use crate::visualization::bird_vis::BirdVis;
+use crate::visualization::obstacle_vis::ObstacleMap;
+use crate::visualization::predator_vis::PredatorVis;
 use krabmaga::bevy::ecs as bevy_ecs;
 use krabmaga::bevy::ecs::system::Resource;
 
@@ -20,12 +24,13 @@ pub struct VisState;
 impl VisualizationState<Flocker> for VisState {
     fn on_init(
         &self,
-        _commands: &mut Commands,
-        _sprite_render_factory: &mut AssetHandleFactoryResource,
-        _state: &mut Flocker,
+        commands: &mut Commands,
+        sprite_render_factory: &mut AssetHandleFactoryResource,
+        state: &mut Flocker,
         _schedule: &mut Schedule,
-        _sim: &mut SimulationDescriptor,
+        sim: &mut SimulationDescriptor,
     ) {
+        ObstacleMap::new(state).render(sprite_render_factory, commands, sim);
     }
 
     fn get_agent_render(
@@ -33,12 +38,13 @@ impl VisualizationState<Flocker> for VisState {
         agent: &Box<dyn Agent>,
         _state: &Flocker,
     ) -> Option<Box<dyn AgentRender>> {
-        // In a multi-agent model, you may want to do so
-        // if let Some(bird) = agent.as_any().downcast_ref::<Bird>() {Box::new(BirdVis);} etc...
-        // We only have one agent here so we can directly return the correct AgentRender
-        Some(Box::new(BirdVis {
-            id: agent.downcast_ref::<Bird>().unwrap().id,
-        }))
+        if let Some(predator) = agent.downcast_ref::<Predator>() {
+            Some(Box::new(PredatorVis { id: predator.id }))
+        } else {
+            Some(Box::new(BirdVis {
+                id: agent.downcast_ref::<Bird>().unwrap().id,
+            }))
+        }
     }
 
     fn get_agent(
@@ -49,13 +55,24 @@ impl VisualizationState<Flocker> for VisState {
         // TODO we don't just need the agent associated to the agent render, we need the correct one so that we
         // can access data such as location etc...
         let state = state.as_any().downcast_ref::<Flocker>().unwrap();
-        match state.field1.get(&Bird::new(
-            agent_render.get_id(),
-            Real2D { x: 0., y: 0. },
-            Real2D { x: 0., y: 0. },
-        )) {
-            Some(matching_agent) => Some(Box::new(*matching_agent)),
-            None => None,
+        if let Some(_predator_vis) = agent_render.downcast_ref::<PredatorVis>() {
+            match state.predators.get(&Predator::new(
+                agent_render.get_id(),
+                Real2D { x: 0., y: 0. },
+                Real2D { x: 0., y: 0. },
+            )) {
+                Some(matching_agent) => Some(Box::new(*matching_agent)),
+                None => None,
+            }
+        } else {
+            match state.field1.get(&Bird::new(
+                agent_render.get_id(),
+                Real2D { x: 0., y: 0. },
+                Real2D { x: 0., y: 0. },
+            )) {
+                Some(matching_agent) => Some(Box::new(*matching_agent)),
+                None => None,
+            }
         }
     }
 }
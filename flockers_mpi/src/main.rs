@@ -4,6 +4,7 @@ use krabmaga::cfg_if;
 cfg_if! {
     if #[cfg(any(feature = "distributed_mpi"))]
     {
+        use crate::model::params::FlockerParams;
         use crate::model::state::Flocker;
         use krabmaga::UNIVERSE;
 
@@ -18,14 +19,10 @@ cfg_if! {
         };
 
 
-        pub static COHESION: f32 = 0.8;
-        pub static AVOIDANCE: f32 = 1.0;
-        pub static RANDOMNESS: f32 = 1.1;
-        pub static CONSISTENCY: f32 = 0.7;
-        pub static MOMENTUM: f32 = 1.0;
-        pub static JUMP: f32 = 0.7;
-        pub static DISCRETIZATION: f32 = 10.0 / 1.5;
-        pub static TOROIDAL: bool = true;
+        fn exit_with_error(err: String) -> ! {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
 
         // Main used when only the simulation should run, without any visualization.
         #[cfg(not(any(feature = "visualization", feature = "visualization_wasm", feature = "distributed_mpi")))]
@@ -40,7 +37,14 @@ cfg_if! {
             let dim = (100., 100.);
             let num_agents = 1000;
 
-            let state = Flocker::new(dim, num_agents);
+            // Every process reads the same parameters: defaults, then an optional
+            // `--config <file>`, then single `--<name> <value>` overrides.
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            let params = FlockerParams::default()
+                .with_args(&args, &[])
+                .unwrap_or_else(|err| exit_with_error(err));
+
+            let state = Flocker::new(dim, num_agents, params);
             let _ = simulate_mpi!(state, step, 1, Info::Normal);
         }
     }
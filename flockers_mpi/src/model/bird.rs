@@ -19,7 +19,6 @@ cfg_if! {
         use std::hash::{Hash, Hasher};
 
         use crate::model::state::Flocker;
-        use crate::{AVOIDANCE, COHESION, CONSISTENCY, JUMP, MOMENTUM, RANDOMNESS};
 
         #[derive(Clone, Copy)]
         pub struct Bird {
@@ -68,9 +67,10 @@ cfg_if! {
 
                 let world = UNIVERSE.world();
 
-                let vec = state
-                    .field1
-                    .get_distributed_neighbors_within_relax_distance(self.loc, 10.0);
+                let vec = state.field1.get_distributed_neighbors_within_relax_distance(
+                    self.loc,
+                    state.params.neighbour_radius,
+                );
 
                 let width = state.dim.0;
                 let height = state.dim.1;
@@ -157,22 +157,23 @@ cfg_if! {
                 }
 
                 let mom = self.last_d;
-
-                let mut dx = COHESION * cohesion.x
-                    + AVOIDANCE * avoidance.x
-                    + CONSISTENCY * consistency.x
-                    + RANDOMNESS * randomness.x
-                    + MOMENTUM * mom.x;
-                let mut dy = COHESION * cohesion.y
-                    + AVOIDANCE * avoidance.y
-                    + CONSISTENCY * consistency.y
-                    + RANDOMNESS * randomness.y
-                    + MOMENTUM * mom.y;
+                let params = &state.params;
+
+                let mut dx = params.cohesion * cohesion.x
+                    + params.avoidance * avoidance.x
+                    + params.consistency * consistency.x
+                    + params.randomness * randomness.x
+                    + params.momentum * mom.x;
+                let mut dy = params.cohesion * cohesion.y
+                    + params.avoidance * avoidance.y
+                    + params.consistency * consistency.y
+                    + params.randomness * randomness.y
+                    + params.momentum * mom.y;
 
                 let dis = (dx * dx + dy * dy).sqrt();
                 if dis > 0.0 {
-                    dx = dx / dis * JUMP;
-                    dy = dy / dis * JUMP;
+                    dx = dx / dis * params.jump;
+                    dy = dy / dis * params.jump;
                 }
 
                 self.last_d = Real2D { x: dx, y: dy };
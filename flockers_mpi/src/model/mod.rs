@@ -1,2 +1,3 @@
 pub mod bird;
+pub mod params;
 pub mod state;
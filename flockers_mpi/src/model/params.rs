@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Weights of the Boids rule and geometry of the field. Values can be loaded from a TOML or JSON
+// file (`--config <file>`) and then overridden one by one from the command line
+// (`--<name> <value>`). Fields missing from the file keep their default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlockerParams {
+    // Weights of the terms of the Boids rule
+    pub cohesion: f32,
+    pub avoidance: f32,
+    pub randomness: f32,
+    pub consistency: f32,
+    pub momentum: f32,
+    // Distance flown by a bird at every step
+    pub jump: f32,
+    // Size of the cells of the field, used to look up the neighbours. The field is always toroidal.
+    pub discretization: f32,
+    // Distance within which birds see their neighbours
+    pub neighbour_radius: f32,
+}
+
+impl Default for FlockerParams {
+    fn default() -> Self {
+        FlockerParams {
+            cohesion: 0.8,
+            avoidance: 1.0,
+            randomness: 1.1,
+            consistency: 0.7,
+            momentum: 1.0,
+            jump: 0.7,
+            discretization: 10.0 / 1.5,
+            neighbour_radius: 10.0,
+        }
+    }
+}
+
+impl FlockerParams {
+    // Load the parameters from a file. Files ending in `.json` are parsed as JSON, anything else as TOML.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<FlockerParams, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+        } else {
+            toml::from_str(&content)
+                .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+        }
+    }
+
+    // Apply the command line arguments (program name excluded) to `self`. `--config <file>`
+    // replaces the current values with the ones of the file, and `--<name> <value>` (or
+    // `--<name>=<value>`) overrides a single parameter. The other options of the simulation, such
+    // as `--model`, are listed in `options` and left to the caller; any other name is an error.
+    pub fn with_args(self, args: &[String], options: &[&str]) -> Result<FlockerParams, String> {
+        let mut params = self;
+        let mut overrides: Vec<(String, String)> = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("Unexpected argument {}", arg))?;
+            // Every option of the simulation takes a value
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for --{}", name))?;
+                    (name.to_string(), value.clone())
+                }
+            };
+
+            if name == "config" {
+                params = FlockerParams::from_file(&value)?;
+            } else if !options.contains(&name.as_str()) {
+                overrides.push((name.replace('-', "_"), value));
+            }
+        }
+
+        if !overrides.is_empty() {
+            params = apply_overrides(params, overrides)?;
+        }
+        params.validate()?;
+        Ok(params)
+    }
+
+    // Reject the sizes the fields and the steering rules cannot work with.
+    pub fn validate(&self) -> Result<(), String> {
+        let sizes = [
+            ("jump", self.jump),
+            ("discretization", self.discretization),
+            ("neighbour_radius", self.neighbour_radius),
+        ];
+        for (name, value) in sizes {
+            if value.is_nan() || value <= 0. {
+                return Err(format!(
+                    "Invalid value for --{}: {}, it must be positive",
+                    name, value
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Apply the `--<name> <value>` overrides to the parameters.
+fn apply_overrides(
+    params: FlockerParams,
+    overrides: Vec<(String, String)>,
+) -> Result<FlockerParams, String> {
+    let mut table =
+        toml::Table::try_from(&params).map_err(|e| format!("Invalid parameters: {}", e))?;
+    for (name, value) in overrides {
+        if !table.contains_key(&name) {
+            return Err(format!("Unknown option --{}", name));
+        }
+        table.insert(name, parse_value(&value));
+    }
+    table
+        .try_into()
+        .map_err(|e| format!("Invalid parameters: {}", e))
+}
+
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("v = {}", value))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
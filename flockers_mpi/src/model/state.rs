@@ -4,7 +4,7 @@ cfg_if! {
     if #[cfg(any(feature = "distributed_mpi"))]
     {
         use crate::model::bird::Bird;
-        use crate::DISCRETIZATION;
+        use crate::model::params::FlockerParams;
         use krabmaga::engine::fields::field::Field;
         use krabmaga::engine::fields::kdtree_mpi::Kdtree;
         use krabmaga::engine::location::Real2D;
@@ -22,16 +22,18 @@ cfg_if! {
             pub step: u64,
             pub field1: Kdtree<Bird>,
             pub initial_flockers: u32,
+            pub params: FlockerParams,
             pub dim: (f32, f32),
         }
 
         impl Flocker {
             #[allow(dead_code)]
-            pub fn new(dim: (f32, f32), initial_flockers: u32) -> Self {
+            pub fn new(dim: (f32, f32), initial_flockers: u32, params: FlockerParams) -> Self {
                 Flocker {
                     step: 0,
-                    field1: Kdtree::create_tree(0, 0.0, 0.0, dim.0, dim.1, DISCRETIZATION, 25.),
+                    field1: Kdtree::create_tree(0, 0.0, 0.0, dim.0, dim.1, params.discretization, 25.),
                     initial_flockers,
+                    params,
                     dim,
                 }
             }
@@ -40,7 +42,7 @@ cfg_if! {
         impl State for Flocker {
             fn reset(&mut self) {
                 self.step = 0;
-                self.field1 = Kdtree::create_tree(0, 0.0, 0.0, self.dim.0, self.dim.1, DISCRETIZATION, 25.)
+                self.field1 = Kdtree::create_tree(0, 0.0, 0.0, self.dim.0, self.dim.1, self.params.discretization, 25.)
             }
 
             ///This function creates the initial agents of the simulation.
@@ -0,0 +1,360 @@
+// Companion tool of the Wolf Sheep Grass model: it runs the simulation (or reads the output file of
+// a previous run) and compares the sheep/wolves dynamics with the Lotka-Volterra equations
+//     dS/dt = alpha * S - beta * S * W
+//     dW/dt = delta * S * W - gamma * W
+// It reports the fitted parameters, the period and amplitude of the oscillations and the extinction
+// times, and writes the phase plane (sheep vs wolves, observed and fitted) as CSV.
+//
+// cargo run --release --bin lotka_volterra -- [--input <csv>] [--phase_plane <csv>] [model parameters]
+
+use krabmaga::engine::schedule::Schedule;
+use krabmaga::engine::state::State;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use wolfsheepgrass::model::params::{take_arg, WsgParams};
+use wolfsheepgrass::model::recorder::StepRecord;
+use wolfsheepgrass::model::state::WsgState;
+
+// Integration sub-steps of the fitted equations for each simulation step
+const SUBSTEPS: usize = 10;
+
+struct Fit {
+    alpha: f64,
+    beta: f64,
+    gamma: f64,
+    delta: f64,
+    // Coefficients of determination of the two regressions
+    r2_sheep: f64,
+    r2_wolves: f64,
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let input = take_arg(&mut args, "--input").unwrap_or_else(|err| exit_with_error(err));
+    let phase_plane = take_arg(&mut args, "--phase_plane")
+        .unwrap_or_else(|err| exit_with_error(err))
+        .unwrap_or_else(|| String::from("phase_plane.csv"));
+
+    let records = match input {
+        Some(input) => read_records(&input),
+        None => {
+            let params = WsgParams::default()
+                .with_args(args)
+                .unwrap_or_else(|err| exit_with_error(err));
+            run(params)
+        }
+    };
+    if records.len() < 3 {
+        exit_with_error(String::from("At least 3 steps are needed for the analysis"));
+    }
+
+    let sheep: Vec<f64> = records.iter().map(|r| r.sheep as f64).collect();
+    let wolves: Vec<f64> = records.iter().map(|r| r.wolves as f64).collect();
+
+    println!("Steps analysed: {}", records.len());
+    let fit = fit(&records);
+    match &fit {
+        Some(fit) => {
+            println!(
+                "Lotka-Volterra fit: alpha = {:.6}, beta = {:.6e}, gamma = {:.6}, delta = {:.6e}",
+                fit.alpha, fit.beta, fit.gamma, fit.delta
+            );
+            println!(
+                "R^2: sheep growth rate = {:.4}, wolves growth rate = {:.4}",
+                fit.r2_sheep, fit.r2_wolves
+            );
+            if fit.alpha > 0. && fit.gamma > 0. {
+                println!(
+                    "Predicted period of small oscillations: {:.2} steps",
+                    2. * PI / (fit.alpha * fit.gamma).sqrt()
+                );
+            }
+        }
+        None => println!("Lotka-Volterra fit: not enough steps with both species alive"),
+    }
+
+    for (name, series) in [("sheep", &sheep), ("wolves", &wolves)] {
+        match oscillation(series) {
+            Some((period, amplitude)) => println!(
+                "Oscillations of {}: period = {:.2} steps, amplitude = {:.2}",
+                name, period, amplitude
+            ),
+            None => println!("Oscillations of {}: none detected", name),
+        }
+    }
+
+    let extinction = |series: &[f64]| {
+        records
+            .iter()
+            .zip(series)
+            .find(|(_, n)| **n == 0.)
+            .map(|(r, _)| r.step.to_string())
+            .unwrap_or_else(|| String::from("never"))
+    };
+    println!("Extinction of sheep: {}", extinction(&sheep));
+    println!("Extinction of wolves: {}", extinction(&wolves));
+
+    if let Err(err) = write_phase_plane(&phase_plane, &records, fit.as_ref()) {
+        exit_with_error(err);
+    }
+    println!("Phase plane written to {}", phase_plane);
+}
+
+fn exit_with_error(err: String) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
+
+// Remove `<name> <value>` from the command line arguments, returning the value.
+fn run(params: WsgParams) -> Vec<StepRecord> {
+    let step = params.step;
+    let mut state = WsgState::new(params);
+    state
+        .load_terrain()
+        .unwrap_or_else(|err| exit_with_error(err));
+    let mut schedule = Schedule::new();
+    state.init(&mut schedule);
+    for _ in 0..step {
+        schedule.step(&mut state);
+    }
+    state.history
+}
+
+fn read_records(path: &str) -> Vec<StepRecord> {
+    let mut reader = csv::Reader::from_path(path)
+        .unwrap_or_else(|e| exit_with_error(format!("Unable to read {}: {}", path, e)));
+    reader
+        .deserialize()
+        .collect::<Result<Vec<StepRecord>, _>>()
+        .unwrap_or_else(|e| exit_with_error(format!("Unable to read {}: {}", path, e)))
+}
+
+// Least squares line y = intercept + slope * x, with its coefficient of determination.
+fn linear_regression(points: &[(f64, f64)]) -> Option<(f64, f64, f64)> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let syy: f64 = points.iter().map(|p| (p.1 - mean_y).powi(2)).sum();
+    if sxx == 0. {
+        return None;
+    }
+    let slope = sxy / sxx;
+    let r2 = if syy == 0. {
+        1.
+    } else {
+        sxy * sxy / (sxx * syy)
+    };
+    Some((mean_y - slope * mean_x, slope, r2))
+}
+
+// Fit the equations on the per capita growth rates: between two steps ln(S1 / S0) is linear in the
+// mean number of wolves (alpha - beta * W) and ln(W1 / W0) in the mean number of sheep (delta * S - gamma).
+fn fit(records: &[StepRecord]) -> Option<Fit> {
+    let mut sheep_points = Vec::new();
+    let mut wolves_points = Vec::new();
+    for pair in records.windows(2) {
+        let (s0, w0) = (pair[0].sheep as f64, pair[0].wolves as f64);
+        let (s1, w1) = (pair[1].sheep as f64, pair[1].wolves as f64);
+        if s0 > 0. && s1 > 0. && w0 > 0. && w1 > 0. {
+            sheep_points.push(((w0 + w1) / 2., (s1 / s0).ln()));
+            wolves_points.push(((s0 + s1) / 2., (w1 / w0).ln()));
+        }
+    }
+
+    let (alpha, minus_beta, r2_sheep) = linear_regression(&sheep_points)?;
+    let (minus_gamma, delta, r2_wolves) = linear_regression(&wolves_points)?;
+    Some(Fit {
+        alpha,
+        beta: -minus_beta,
+        gamma: -minus_gamma,
+        delta,
+        r2_sheep,
+        r2_wolves,
+    })
+}
+
+// Period and amplitude of the oscillations of a series. The period is the mean distance between
+// two upward crossings of the mean value, the amplitude is half the range after the first one,
+// so that the initial transient is ignored. None with less than two crossings.
+fn oscillation(series: &[f64]) -> Option<(f64, f64)> {
+    let mean = series.iter().sum::<f64>() / series.len() as f64;
+    let crossings: Vec<usize> = series
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] < mean && pair[1] >= mean)
+        .map(|(i, _)| i)
+        .collect();
+    if crossings.len() < 2 {
+        return None;
+    }
+    let period =
+        (crossings[crossings.len() - 1] - crossings[0]) as f64 / (crossings.len() - 1) as f64;
+    let cycles = &series[crossings[0]..];
+    let max = cycles.iter().cloned().fold(f64::MIN, f64::max);
+    let min = cycles.iter().cloned().fold(f64::MAX, f64::min);
+    Some((period, (max - min) / 2.))
+}
+
+fn derivatives(fit: &Fit, (s, w): (f64, f64)) -> (f64, f64) {
+    (
+        fit.alpha * s - fit.beta * s * w,
+        fit.delta * s * w - fit.gamma * w,
+    )
+}
+
+// One simulation step of the fitted equations, with the fourth order Runge-Kutta method.
+fn integrate(fit: &Fit, (mut s, mut w): (f64, f64)) -> (f64, f64) {
+    let h = 1. / SUBSTEPS as f64;
+    for _ in 0..SUBSTEPS {
+        let k1 = derivatives(fit, (s, w));
+        let k2 = derivatives(fit, (s + h / 2. * k1.0, w + h / 2. * k1.1));
+        let k3 = derivatives(fit, (s + h / 2. * k2.0, w + h / 2. * k2.1));
+        let k4 = derivatives(fit, (s + h * k3.0, w + h * k3.1));
+        s = (s + h / 6. * (k1.0 + 2. * k2.0 + 2. * k3.0 + k4.0)).max(0.);
+        w = (w + h / 6. * (k1.1 + 2. * k2.1 + 2. * k3.1 + k4.1)).max(0.);
+    }
+    (s, w)
+}
+
+// Observed populations of each step, next to the trajectory of the fitted equations started from
+// the same initial populations (empty columns without a fit).
+fn write_phase_plane(path: &str, records: &[StepRecord], fit: Option<&Fit>) -> Result<(), String> {
+    if let Some(dir) = Path::new(path)
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+    }
+    let mut writer =
+        csv::Writer::from_path(path).map_err(|e| format!("Unable to create {}: {}", path, e))?;
+    let error = |e: csv::Error| format!("Unable to write {}: {}", path, e);
+
+    writer
+        .write_record(["step", "sheep", "wolves", "lv_sheep", "lv_wolves"])
+        .map_err(error)?;
+    let mut lv = (records[0].sheep as f64, records[0].wolves as f64);
+    for record in records {
+        let (lv_sheep, lv_wolves) = match fit {
+            Some(_) => (lv.0.to_string(), lv.1.to_string()),
+            None => (String::new(), String::new()),
+        };
+        writer
+            .write_record([
+                record.step.to_string(),
+                record.sheep.to_string(),
+                record.wolves.to_string(),
+                lv_sheep,
+                lv_wolves,
+            ])
+            .map_err(error)?;
+        if let Some(fit) = fit {
+            lv = integrate(fit, lv);
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("Unable to write {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Populations of `steps` steps of the Lotka-Volterra equations, rounded like the ones of a run.
+    fn lotka_volterra(fit: &Fit, start: (f64, f64), steps: u64) -> Vec<StepRecord> {
+        let mut populations = start;
+        (0..steps)
+            .map(|step| {
+                let record = StepRecord {
+                    step,
+                    sheep: populations.0.round() as u64,
+                    wolves: populations.1.round() as u64,
+                    ..StepRecord::default()
+                };
+                populations = integrate(fit, populations);
+                record
+            })
+            .collect()
+    }
+
+    fn equations(alpha: f64, beta: f64, gamma: f64, delta: f64) -> Fit {
+        Fit {
+            alpha,
+            beta,
+            gamma,
+            delta,
+            r2_sheep: 1.,
+            r2_wolves: 1.,
+        }
+    }
+
+    fn assert_close(value: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (value - expected).abs() <= tolerance * expected.abs(),
+            "{} is not within {} of {}",
+            value,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn fit_recovers_the_parameters_of_the_equations() {
+        let expected = equations(0.1, 0.0002, 0.1, 0.0001);
+        let records = lotka_volterra(&expected, (800., 400.), 300);
+        let fit = fit(&records).unwrap();
+        assert_close(fit.alpha, expected.alpha, 0.05);
+        assert_close(fit.beta, expected.beta, 0.05);
+        assert_close(fit.gamma, expected.gamma, 0.05);
+        assert_close(fit.delta, expected.delta, 0.05);
+        assert!(fit.r2_sheep > 0.99 && fit.r2_wolves > 0.99);
+    }
+
+    #[test]
+    fn fit_needs_both_species() {
+        let records = lotka_volterra(&equations(0.1, 0.0002, 0.1, 0.0001), (800., 0.), 50);
+        assert!(fit(&records).is_none());
+    }
+
+    #[test]
+    fn integrate_keeps_the_equilibrium_and_the_first_integral() {
+        let fit = equations(0.1, 0.0002, 0.1, 0.0001);
+        // Equilibrium: S = gamma / delta, W = alpha / beta
+        let (s, w) = integrate(&fit, (1000., 500.));
+        assert_close(s, 1000., 1e-12);
+        assert_close(w, 500., 1e-12);
+
+        // delta * S - gamma * ln S + beta * W - alpha * ln W is constant along a trajectory
+        let first_integral = |(s, w): (f64, f64)| {
+            fit.delta * s - fit.gamma * s.ln() + fit.beta * w - fit.alpha * w.ln()
+        };
+        let start = (800., 400.);
+        let mut populations = start;
+        for _ in 0..200 {
+            populations = integrate(&fit, populations);
+        }
+        assert_close(first_integral(populations), first_integral(start), 1e-9);
+    }
+
+    #[test]
+    fn oscillation_measures_period_and_amplitude() {
+        // No sample on the mean, whose crossings would depend on rounding
+        let series: Vec<f64> = (0..200)
+            .map(|i| 10. + 3. * (2. * PI * (i as f64 + 0.5) / 20.).sin())
+            .collect();
+        let (period, amplitude) = oscillation(&series).unwrap();
+        assert_close(period, 20., 1e-9);
+        // Sampled half a step away from the peaks
+        assert_close(amplitude, 3. * (PI / 20.).cos(), 1e-9);
+
+        let growth: Vec<f64> = (0..200).map(|i| i as f64).collect();
+        assert!(oscillation(&growth).is_none());
+    }
+}
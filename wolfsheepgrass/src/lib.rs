@@ -0,0 +1,6 @@
+// The Wolf Sheep Grass model, shared by the simulation and the analysis tools in src/bin, and its
+// visualization.
+pub mod model;
+
+#[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
+pub mod visualization;
@@ -3,56 +3,139 @@ use krabmaga::*;
 
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
 use {
-    crate::visualization::vis_state::VisState, krabmaga::bevy::prelude::Color,
+    krabmaga::bevy::prelude::Color,
     krabmaga::bevy::prelude::FixedUpdate,
-    krabmaga::engine::fields::dense_number_grid_2d::DenseNumberGrid2D,
     krabmaga::visualization::fields::number_grid_2d::BatchRender,
     krabmaga::visualization::visualization::Visualization,
+    wolfsheepgrass::visualization::{grass_vis::GrassGrowth, vis_state::VisState},
 };
 
-use crate::model::state::WsgState;
-
-mod model;
-
-pub const ENERGY_CONSUME: f64 = 1.0;
-
-pub const FULL_GROWN: u16 = 20;
-
-pub const GAIN_ENERGY_SHEEP: f64 = 4.0;
-pub const GAIN_ENERGY_WOLF: f64 = 20.0;
+use wolfsheepgrass::model::params::WsgParams;
+use wolfsheepgrass::model::state::WsgState;
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+use wolfsheepgrass::model::{checkpoint::Checkpoint, params::take_arg};
 
-pub const SHEEP_REPR: f64 = 0.2;
-pub const WOLF_REPR: f64 = 0.1;
+fn exit_with_error(err: String) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
 
-pub const MOMENTUM_PROBABILITY: f64 = 0.8;
+// The state of a headless run and the number of steps left to execute. Parameters are the defaults,
+// then an optional `--config <file>`, then single `--<name> <value>` overrides; with
+// `--resume <file>` the run continues from a checkpoint instead.
+#[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
+fn load_state(mut args: Vec<String>) -> Result<(WsgState, u64), String> {
+    let (mut state, step) = match take_arg(&mut args, "--resume")? {
+        // The parameters of the checkpoint can still be overridden, e.g. to run more steps
+        Some(file) => {
+            let checkpoint = Checkpoint::load(&file)?;
+            let params = checkpoint.params.clone().with_args(args)?;
+            if params.dim != checkpoint.params.dim {
+                return Err(String::from("The grid size of a resumed run cannot change"));
+            }
+            let step = params.step.saturating_sub(checkpoint.step);
+            (WsgState::resume(params, checkpoint), step)
+        }
+        None => {
+            let params = WsgParams::default().with_args(args)?;
+            let step = params.step;
+            (WsgState::new(params), step)
+        }
+    };
+    state.load_terrain()?;
+    Ok((state, step))
+}
 
 #[cfg(not(any(feature = "visualization", feature = "visualization_wasm")))]
 fn main() {
-    let step = 200;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (state, step) = load_state(args).unwrap_or_else(|err| exit_with_error(err));
 
-    let dim: (i32, i32) = (5000, 5000);
-    let initial_animals: (u32, u32) = ((2000000. * 0.6) as u32, (2000000. * 0.4) as u32);
-
-    let state = WsgState::new(dim, initial_animals);
-    let _ = simulate!(state, step, 1);
+    let error = state.error.clone();
+    simulate!(state, step, 1);
+    let error = error.lock().unwrap().take();
+    if let Some(err) = error {
+        exit_with_error(err);
+    }
 }
 
-#[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
-mod visualization;
-
 // Main used when a visualization feature is applied
 #[cfg(any(feature = "visualization", feature = "visualization_wasm"))]
 fn main() {
-    let dim: (i32, i32) = (25, 25);
-
-    let initial_animals: (u32, u32) = ((60. * 0.6) as u32, (60. * 0.4) as u32);
+    let params = WsgParams {
+        dim: (25, 25),
+        initial_sheep: (60. * 0.6) as u32,
+        initial_wolves: (60. * 0.4) as u32,
+        ..WsgParams::default()
+    }
+    .with_args(std::env::args().skip(1))
+    .unwrap_or_else(|err| exit_with_error(err));
+    let dim = params.dim;
 
-    let state = WsgState::new(dim, initial_animals);
+    let mut state = WsgState::new(params);
+    state
+        .load_terrain()
+        .unwrap_or_else(|err| exit_with_error(err));
     let mut app = Visualization::default()
         .with_background_color(Color::rgb(255., 255., 255.))
         .with_simulation_dimensions(dim.0 as f32, dim.1 as f32)
         .with_window_dimensions(1000., 700.)
         .setup::<VisState, WsgState>(VisState, state);
-    app.add_systems(FixedUpdate, DenseNumberGrid2D::batch_render);
+    app.add_systems(FixedUpdate, GrassGrowth::batch_render);
     app.run()
 }
+
+#[cfg(all(
+    test,
+    not(any(feature = "visualization", feature = "visualization_wasm"))
+))]
+mod tests {
+    use super::*;
+    use krabmaga::engine::schedule::Schedule;
+    use krabmaga::engine::state::State;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn resume_continues_a_checkpointed_run() {
+        let dir = std::env::temp_dir().join(format!("wsg_resume_{}", std::process::id()));
+        let params = WsgParams {
+            dim: (30, 30),
+            initial_sheep: 60,
+            initial_wolves: 40,
+            seed: 42,
+            step: 50,
+            checkpoint_every: 20,
+            checkpoint_dir: dir.display().to_string(),
+            ..WsgParams::default()
+        };
+        let mut state = WsgState::new(params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        for _ in 0..50 {
+            schedule.step(&mut state);
+        }
+
+        // Checkpoint written by before_step at the beginning of step 20
+        let checkpoint = dir.join("wsg_20.ckpt").display().to_string();
+        let loaded = load_state(args(&["--resume", &checkpoint, "--checkpoint_every=0"]));
+        std::fs::remove_dir_all(&dir).unwrap();
+        let (mut resumed, step) = loaded.unwrap();
+        assert_eq!(step, 30);
+
+        let mut schedule = Schedule::new();
+        resumed.init(&mut schedule);
+        for _ in 0..step {
+            schedule.step(&mut resumed);
+        }
+        assert_eq!(resumed.history, state.history[20..]);
+    }
+
+    #[test]
+    fn resume_needs_a_file() {
+        let err = load_state(args(&["--seed", "1", "--resume"])).err();
+        assert_eq!(err.as_deref(), Some("Missing value for --resume"));
+    }
+}
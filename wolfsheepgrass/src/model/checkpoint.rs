@@ -0,0 +1,204 @@
+use crate::model::params::WsgParams;
+use crate::model::sheep::Sheep;
+use crate::model::state::{schedule_step, WsgState};
+use crate::model::wolf::Wolf;
+use krabmaga::engine::location::Int2D;
+use krabmaga::engine::schedule::Schedule;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+// Snapshot of a run taken at the beginning of a step, before any agent is executed.
+// There is no random number generator state to save: every stream is derived from the seed,
+// the step and the agent id (see WsgState::agent_rng).
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub params: WsgParams,
+    // Step of the schedule that will be executed first after resuming, and time of the last executed one
+    pub step: u64,
+    pub time: f32,
+    pub next_id: u32,
+    // Agents in the schedule
+    pub sheep: Vec<Sheep>,
+    pub wolves: Vec<Wolf>,
+    // Content of the grids, which differs from the schedule: sheep eaten during the last step are
+    // still on the field, animals born during the last step are not on it yet.
+    pub sheep_grid: Vec<Sheep>,
+    pub wolves_grid: Vec<Wolf>,
+    // Grass value of every cell, column by column
+    pub grass: Vec<u16>,
+    pub killed_sheep: Vec<Sheep>,
+    pub meals: Vec<(u32, f64)>,
+}
+
+impl Checkpoint {
+    pub fn new(state: &WsgState, schedule: &Schedule) -> Checkpoint {
+        let mut sheep = Vec::new();
+        let mut wolves = Vec::new();
+        for n in schedule.get_all_events() {
+            if let Some(s) = n.downcast_ref::<Sheep>() {
+                sheep.push(*s);
+            }
+            if let Some(w) = n.downcast_ref::<Wolf>() {
+                wolves.push(*w);
+            }
+        }
+        sheep.sort_unstable_by_key(|s| s.id);
+        wolves.sort_unstable_by_key(|w| w.id);
+
+        let mut sheep_grid = Vec::new();
+        let mut wolves_grid = Vec::new();
+        let mut grass = Vec::with_capacity((state.dim.0 * state.dim.1) as usize);
+        for x in 0..state.dim.0 {
+            for y in 0..state.dim.1 {
+                let loc = Int2D { x, y };
+                if let Some(objects) = state.sheep_grid.get_objects(&loc) {
+                    sheep_grid.extend(objects);
+                }
+                if let Some(objects) = state.wolves_grid.get_objects(&loc) {
+                    wolves_grid.extend(objects);
+                }
+                grass.push(state.grass_field.get_value(&loc).unwrap_or(0));
+            }
+        }
+
+        let mut killed_sheep: Vec<Sheep> = state.killed_sheep.iter().copied().collect();
+        killed_sheep.sort_unstable_by_key(|s| s.id);
+        let mut meals: Vec<(u32, f64)> = state.meals.iter().map(|(id, e)| (*id, *e)).collect();
+        meals.sort_unstable_by_key(|(id, _)| *id);
+
+        Checkpoint {
+            params: state.params.clone(),
+            step: schedule_step(schedule),
+            time: schedule.time,
+            next_id: state.next_id,
+            sheep,
+            wolves,
+            sheep_grid,
+            wolves_grid,
+            grass,
+            killed_sheep,
+            meals,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Checkpoint, String> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        bincode::deserialize_from(BufReader::new(file))
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))
+    }
+
+    // Write the checkpoint into `dir`, named after its step. The file is written under a temporary
+    // name and then renamed, so a crash while saving never leaves a truncated checkpoint.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf, String> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+        let path = dir.join(format!("wsg_{}.ckpt", self.step));
+        let tmp = path.with_extension("ckpt.tmp");
+
+        let file =
+            File::create(&tmp).map_err(|e| format!("Unable to create {}: {}", tmp.display(), e))?;
+        bincode::serialize_into(BufWriter::new(file), self)
+            .map_err(|e| format!("Unable to write {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, &path)
+            .map_err(|e| format!("Unable to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+// Serde helpers for the locations of the agents, Int2D does not implement Serialize.
+pub mod int2d {
+    use krabmaga::engine::location::Int2D;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(loc: &Int2D, serializer: S) -> Result<S::Ok, S::Error> {
+        (loc.x, loc.y).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Int2D, D::Error> {
+        let (x, y) = <(i32, i32)>::deserialize(deserializer)?;
+        Ok(Int2D { x, y })
+    }
+}
+
+pub mod option_int2d {
+    use krabmaga::engine::location::Int2D;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(loc: &Option<Int2D>, serializer: S) -> Result<S::Ok, S::Error> {
+        loc.map(|loc| (loc.x, loc.y)).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Int2D>, D::Error> {
+        let loc = Option::<(i32, i32)>::deserialize(deserializer)?;
+        Ok(loc.map(|(x, y)| Int2D { x, y }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::recorder::StepRecord;
+    use krabmaga::engine::state::State;
+
+    fn params() -> WsgParams {
+        WsgParams {
+            dim: (30, 30),
+            initial_sheep: 60,
+            initial_wolves: 40,
+            seed: 42,
+            ..WsgParams::default()
+        }
+    }
+
+    // Run `steps` steps of `state`, returning the records of the steps executed.
+    fn run(state: &mut WsgState, schedule: &mut Schedule, steps: u64) -> Vec<StepRecord> {
+        for _ in 0..steps {
+            schedule.step(state);
+        }
+        std::mem::take(&mut state.history)
+    }
+
+    // The whole state of a run as bytes. Agents in the same cell of a grid are listed in the order
+    // they were scheduled, which does not matter to the model, so they are sorted by id.
+    fn snapshot(state: &WsgState, schedule: &Schedule) -> Vec<u8> {
+        let mut checkpoint = Checkpoint::new(state, schedule);
+        checkpoint.sheep_grid.sort_unstable_by_key(|sheep| sheep.id);
+        checkpoint.wolves_grid.sort_unstable_by_key(|wolf| wolf.id);
+        bincode::serialize(&checkpoint).unwrap()
+    }
+
+    #[test]
+    fn resumed_run_matches_uninterrupted_run() {
+        let (n, m) = (20, 30);
+
+        let mut state = WsgState::new(params());
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        let records = run(&mut state, &mut schedule, n + m);
+        let expected = snapshot(&state, &schedule);
+
+        let mut state = WsgState::new(params());
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        run(&mut state, &mut schedule, n);
+        let dir = std::env::temp_dir().join(format!("wsg_checkpoint_{}", std::process::id()));
+        let path = Checkpoint::new(&state, &schedule).save(&dir).unwrap();
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut state = WsgState::resume(params(), checkpoint);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        let resumed = run(&mut state, &mut schedule, m);
+
+        assert_eq!(resumed, records[n as usize..]);
+        assert!(snapshot(&state, &schedule) == expected);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::model::sheep::Sheep;
+use crate::model::wolf::Wolf;
+use krabmaga::engine::location::Int2D;
+
+// A wolf trying to eat a sheep on its cell.
+pub struct Predation {
+    pub wolf: u32,
+    pub sheep: Sheep,
+    pub gain_energy: f64,
+}
+
+// A sheep trying to eat the full grown grass of its cell.
+pub struct Grazing {
+    pub sheep: u32,
+    pub loc: Int2D,
+    pub gain_energy: f64,
+}
+
+// Everything the agents report during a step. Agents only get a shared reference to the state,
+// so that they can be executed in parallel: the events are collected here in any order and
+// resolved in after_step, sorted by agent id, so that the outcome of a step does not depend on
+// the order of execution. Offspring are stored with the id of their parent, their own id is
+// assigned when they are scheduled.
+#[derive(Default)]
+pub struct StepEvents {
+    pub sheep_births: Vec<(u32, Sheep)>,
+    pub wolf_births: Vec<(u32, Wolf)>,
+    pub predations: Vec<Predation>,
+    pub grazings: Vec<Grazing>,
+    pub starved_sheep: Vec<Sheep>,
+    pub dead_wolves: Vec<Wolf>,
+}
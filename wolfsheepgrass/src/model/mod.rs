@@ -1,3 +1,10 @@
+pub mod checkpoint;
+pub mod events;
+pub mod movement;
+pub mod params;
+pub mod recorder;
 pub mod sheep;
 pub mod state;
+pub mod terrain;
+pub mod traits;
 pub mod wolf;
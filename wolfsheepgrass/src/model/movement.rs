@@ -0,0 +1,153 @@
+use crate::model::state::WsgState;
+use krabmaga::engine::location::Int2D;
+use krabmaga::rand::rngs::StdRng;
+use krabmaga::rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// How sheep and wolves choose where to move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MovementMode {
+    // Random walk with momentum, the original behaviour of the model
+    #[default]
+    Random,
+    // Animals sense their neighbourhood within the perception radius: wolves chase the nearest
+    // sheep, sheep flee from the nearest wolf or go toward the nearest full grown grass.
+    // Without anything in sight they fall back to the random walk.
+    Directed,
+}
+
+impl WsgState {
+    // Next cell of a sheep, one cell away from its current location at most.
+    pub fn sheep_move(&self, loc: &Int2D, last: Option<Int2D>, rng: &mut StdRng) -> Int2D {
+        if self.params.movement == MovementMode::Directed {
+            if let Some(wolf) =
+                self.nearest(loc, |cell| self.wolves_grid.get_objects(cell).is_some())
+            {
+                if let Some(new_loc) = self.step_toward(loc, &wolf, true) {
+                    return new_loc;
+                }
+            } else if let Some(grass) = self.nearest(loc, |cell| self.is_full_grown(cell)) {
+                if grass == *loc {
+                    return *loc;
+                }
+                if let Some(new_loc) = self.step_toward(loc, &grass, false) {
+                    return new_loc;
+                }
+            }
+        }
+        self.random_move(loc, last, rng)
+    }
+
+    // Next cell of a wolf, one cell away from its current location at most.
+    pub fn wolf_move(&self, loc: &Int2D, last: Option<Int2D>, rng: &mut StdRng) -> Int2D {
+        if self.params.movement == MovementMode::Directed {
+            let sheep = self.nearest(loc, |cell| {
+                self.sheep_grid.get_objects(cell).is_some_and(|sheep| {
+                    sheep.iter().any(|sheep| !self.killed_sheep.contains(sheep))
+                })
+            });
+            if let Some(sheep) = sheep {
+                if sheep == *loc {
+                    return *loc;
+                }
+                if let Some(new_loc) = self.step_toward(loc, &sheep, false) {
+                    return new_loc;
+                }
+            }
+        }
+        self.random_move(loc, last, rng)
+    }
+
+    // Move an animal by one cell: with momentum_probability it keeps the direction it came from
+    // (if that cell can be entered), otherwise it moves to a random neighbouring cell.
+    // Animals stay still instead of entering an impassable cell.
+    pub fn random_move(&self, loc: &Int2D, last: Option<Int2D>, rng: &mut StdRng) -> Int2D {
+        let (x, y) = (loc.x, loc.y);
+        if let Some(last_loc) = last {
+            if rng.random_bool(self.params.momentum_probability) {
+                let new_loc = Int2D {
+                    x: x + (x - last_loc.x),
+                    y: y + (y - last_loc.y),
+                };
+                if self.can_enter(&new_loc) {
+                    return new_loc;
+                }
+            }
+        }
+
+        let xmin = if x > 0 { -1 } else { 0 };
+        let xmax = i32::from(x < self.dim.0 - 1);
+        let ymin = if y > 0 { -1 } else { 0 };
+        let ymax = i32::from(y < self.dim.1 - 1);
+
+        let nx = rng.random_range(xmin..=xmax);
+        let ny = rng.random_range(ymin..=ymax);
+        let new_loc = Int2D {
+            x: x + nx,
+            y: y + ny,
+        };
+        if self.is_passable(&new_loc) {
+            new_loc
+        } else {
+            *loc
+        }
+    }
+
+    fn can_enter(&self, loc: &Int2D) -> bool {
+        loc.x >= 0
+            && loc.x < self.dim.0
+            && loc.y >= 0
+            && loc.y < self.dim.1
+            && self.is_passable(loc)
+    }
+
+    // A cell whose grass can be eaten by a sheep during this step.
+    fn is_full_grown(&self, loc: &Int2D) -> bool {
+        self.grass_field.get_value_unbuffered(loc).is_none()
+            && self
+                .grass_field
+                .get_value(loc)
+                .is_some_and(|grass| grass > 0 && grass >= self.grass_capacity(loc))
+    }
+
+    // The nearest cell within the perception radius (Euclidean distance) that satisfies `sensed`,
+    // ties are broken by scanning order so that the choice is deterministic.
+    fn nearest<F>(&self, loc: &Int2D, sensed: F) -> Option<Int2D>
+    where
+        F: Fn(&Int2D) -> bool,
+    {
+        let radius = self.params.perception_radius;
+        let mut nearest: Option<(i32, Int2D)> = None;
+        for x in (loc.x - radius).max(0)..=(loc.x + radius).min(self.dim.0 - 1) {
+            for y in (loc.y - radius).max(0)..=(loc.y + radius).min(self.dim.1 - 1) {
+                let (dx, dy) = (x - loc.x, y - loc.y);
+                let distance = dx * dx + dy * dy;
+                if distance > radius * radius || nearest.is_some_and(|(best, _)| best <= distance) {
+                    continue;
+                }
+                let cell = Int2D { x, y };
+                if sensed(&cell) {
+                    nearest = Some((distance, cell));
+                }
+            }
+        }
+        nearest.map(|(_, cell)| cell)
+    }
+
+    // One cell toward (or away from) the target. When the diagonal cell cannot be entered the two
+    // cells along the axes are tried; None if the animal is stuck.
+    fn step_toward(&self, loc: &Int2D, target: &Int2D, away: bool) -> Option<Int2D> {
+        let sign = if away { -1 } else { 1 };
+        let dx = (target.x - loc.x).signum() * sign;
+        let dy = (target.y - loc.y).signum() * sign;
+        [(dx, dy), (dx, 0), (0, dy)]
+            .into_iter()
+            .filter(|&(nx, ny)| nx != 0 || ny != 0)
+            .map(|(nx, ny)| Int2D {
+                x: loc.x + nx,
+                y: loc.y + ny,
+            })
+            .find(|new_loc| self.can_enter(new_loc))
+    }
+}
@@ -0,0 +1,358 @@
+use crate::model::movement::MovementMode;
+use crate::model::terrain::TerrainParams;
+use crate::model::traits::MutationParams;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Every knob of the model. Values can be loaded from a TOML or JSON file (`--config <file>`) and
+// then overridden one by one from the command line (`--<name> <value>`), so that an experiment
+// never requires a recompilation. Fields missing from the file keep their default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WsgParams {
+    // Width and height of the grid.
+    pub dim: (i32, i32),
+    pub initial_sheep: u32,
+    pub initial_wolves: u32,
+    // Number of steps of a headless run.
+    pub step: u64,
+    // Master seed of the simulation: the same seed and parameters always produce the same run.
+    pub seed: u64,
+    // Energy lost by each animal at every step.
+    pub energy_consume: f64,
+    // Value of a grass cell that can be eaten by a sheep.
+    pub full_grown: u16,
+    pub gain_energy_sheep: f64,
+    pub gain_energy_wolf: f64,
+    pub sheep_repr: f64,
+    pub wolf_repr: f64,
+    // Probability of keeping the last direction of movement.
+    pub momentum_probability: f64,
+    // Random walk (default) or directed hunting and fleeing, see MovementMode.
+    pub movement: MovementMode,
+    // Distance, in cells, within which animals sense prey, predators and grass in directed mode.
+    pub perception_radius: i32,
+    // Initial speed of the animals, in cells per step. Faster animals explore more but
+    // consume more energy.
+    pub speed_sheep: f64,
+    pub speed_wolf: f64,
+    // Mutation of the heritable traits (gain energy, reproduction probability and speed).
+    pub mutation: MutationParams,
+    // Optional terrain layer. Without it grass grows everywhere by 1 per step up to full_grown.
+    pub terrain: Option<TerrainParams>,
+    // File where the population dynamics are recorded, one row per step (see Recorder).
+    pub output: Option<String>,
+    // Save a checkpoint every checkpoint_every steps (0 disables them) into checkpoint_dir.
+    pub checkpoint_every: u64,
+    pub checkpoint_dir: String,
+}
+
+impl Default for WsgParams {
+    fn default() -> Self {
+        WsgParams {
+            dim: (5000, 5000),
+            initial_sheep: (2000000. * 0.6) as u32,
+            initial_wolves: (2000000. * 0.4) as u32,
+            step: 200,
+            seed: 0,
+            energy_consume: 1.0,
+            full_grown: 20,
+            gain_energy_sheep: 4.0,
+            gain_energy_wolf: 20.0,
+            sheep_repr: 0.2,
+            wolf_repr: 0.1,
+            momentum_probability: 0.8,
+            movement: MovementMode::Random,
+            perception_radius: 3,
+            speed_sheep: 1.0,
+            speed_wolf: 1.0,
+            mutation: MutationParams::default(),
+            terrain: None,
+            output: None,
+            checkpoint_every: 0,
+            checkpoint_dir: String::from("checkpoints"),
+        }
+    }
+}
+
+impl WsgParams {
+    // Load the parameters from a file. Files ending in `.json` are parsed as JSON, anything else as TOML.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<WsgParams, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+        } else {
+            toml::from_str(&content)
+                .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))
+        }
+    }
+
+    // Build the parameters from the command line arguments (program name excluded), starting from `self`.
+    // `--config <file>` replaces the current values with the ones of the file, while every other
+    // `--<name> <value>` (or `--<name>=<value>`) pair overrides a single parameter. Nested parameters
+    // are addressed with dots (`--table.name <value>`) and values use the TOML syntax,
+    // so `--dim 100,100` and `--dim [100,100]` are both accepted.
+    pub fn with_args<I>(self, args: I) -> Result<WsgParams, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut params = self;
+        let mut overrides: Vec<(String, String)> = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("Unexpected argument {}", arg))?;
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for --{}", name))?;
+                    (name.to_string(), value)
+                }
+            };
+
+            if name == "config" {
+                params = WsgParams::from_file(&value)?;
+            } else {
+                overrides.push((name.replace('-', "_"), value));
+            }
+        }
+
+        if !overrides.is_empty() {
+            params = apply_overrides(params, overrides)?;
+        }
+        params.validate()?;
+        Ok(params)
+    }
+
+    // Reject the values the model cannot run with.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.dim.0 <= 0 || self.dim.1 <= 0 {
+            return Err(format!(
+                "Invalid value for --dim: {},{}, both sizes must be positive",
+                self.dim.0, self.dim.1
+            ));
+        }
+        let probabilities = [
+            ("sheep_repr", self.sheep_repr),
+            ("wolf_repr", self.wolf_repr),
+            ("momentum_probability", self.momentum_probability),
+        ];
+        for (name, value) in probabilities {
+            if !(0. ..=1.).contains(&value) {
+                return Err(format!(
+                    "Invalid value for --{}: {}, expected a probability between 0 and 1",
+                    name, value
+                ));
+            }
+        }
+        let non_negative = [
+            ("energy_consume", self.energy_consume),
+            ("gain_energy_sheep", self.gain_energy_sheep),
+            ("gain_energy_wolf", self.gain_energy_wolf),
+            ("speed_sheep", self.speed_sheep),
+            ("speed_wolf", self.speed_wolf),
+        ];
+        for (name, value) in non_negative {
+            if value < 0. || value.is_nan() {
+                return Err(format!(
+                    "Invalid value for --{}: {}, it cannot be negative",
+                    name, value
+                ));
+            }
+        }
+        if self.movement == MovementMode::Directed && self.perception_radius <= 0 {
+            return Err(format!(
+                "Invalid value for --perception_radius: {}, directed movement needs a positive radius",
+                self.perception_radius
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Remove `<name> <value>` (or `<name>=<value>`) from the command line arguments, returning the
+// value, for the options that are not parameters of the model.
+pub fn take_arg(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let prefix = format!("{}=", name);
+    let Some(i) = args
+        .iter()
+        .position(|arg| arg == name || arg.starts_with(&prefix))
+    else {
+        return Ok(None);
+    };
+    let arg = args.remove(i);
+    if let Some(value) = arg.strip_prefix(&prefix) {
+        return Ok(Some(value.to_string()));
+    }
+    if i >= args.len() {
+        return Err(format!("Missing value for {}", name));
+    }
+    Ok(Some(args.remove(i)))
+}
+
+// Apply the `--<name> <value>` overrides to the parameters.
+fn apply_overrides(
+    params: WsgParams,
+    overrides: Vec<(String, String)>,
+) -> Result<WsgParams, String> {
+    // The overrides are applied on a JSON value rather than a TOML one: TOML integers are i64,
+    // which would reject seeds above i64::MAX.
+    let mut table =
+        serde_json::to_value(&params).map_err(|e| format!("Invalid parameters: {}", e))?;
+    for (name, value) in overrides {
+        set_value(&mut table, &name, parse_value(&value))?;
+    }
+    serde_json::from_value(table).map_err(|e| format!("Invalid parameters: {}", e))
+}
+
+// Parse a value written with the TOML syntax. Integers are parsed first, so that the whole u64
+// range is accepted.
+fn parse_value(value: &str) -> serde_json::Value {
+    if let Ok(n) = value.parse::<u64>() {
+        return n.into();
+    }
+    let parse = |v: &str| {
+        toml::from_str::<toml::Table>(&format!("v = {}", v))
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .and_then(|v| serde_json::to_value(v).ok())
+    };
+    parse(value)
+        .or_else(|| parse(&format!("[{}]", value)))
+        .unwrap_or_else(|| serde_json::Value::String(value.to_string()))
+}
+
+fn set_value(
+    table: &mut serde_json::Value,
+    name: &str,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let mut current = table;
+    let mut keys = name.split('.').peekable();
+    while let Some(key) = keys.next() {
+        let table = current
+            .as_object_mut()
+            .ok_or_else(|| format!("Unknown parameter --{}", name))?;
+        if keys.peek().is_none() {
+            // Unset optional values are null, unknown names are rejected when the table is
+            // converted back into the parameters.
+            table.insert(key.to_string(), value);
+            return Ok(());
+        }
+        current = table
+            .get_mut(key)
+            .ok_or_else(|| format!("Unknown parameter --{}", name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn overrides_keep_the_whole_u64_range() {
+        let params = WsgParams::default()
+            .with_args(args(&["--seed", "18446744073709551615", "--dim", "100,50"]))
+            .unwrap();
+        assert_eq!(params.seed, u64::MAX);
+        assert_eq!(params.dim, (100, 50));
+
+        let params = WsgParams {
+            seed: u64::MAX,
+            ..WsgParams::default()
+        }
+        .with_args(args(&[
+            "--energy_consume=0.5",
+            "--mutation.speed",
+            "0.1",
+            "--output",
+            "out.csv",
+        ]))
+        .unwrap();
+        assert_eq!(params.seed, u64::MAX);
+        assert_eq!(params.energy_consume, 0.5);
+        assert_eq!(params.mutation.speed, 0.1);
+        assert_eq!(params.output.as_deref(), Some("out.csv"));
+    }
+
+    #[test]
+    fn unknown_parameters_are_rejected() {
+        let err = WsgParams::default()
+            .with_args(args(&["--sheep", "10"]))
+            .unwrap_err();
+        assert!(err.starts_with("Invalid parameters: unknown field `sheep`"));
+    }
+
+    #[test]
+    fn options_are_taken_in_both_forms() {
+        let mut values = args(&["--input=a.csv", "--seed", "3", "--phase_plane", "b.csv"]);
+        assert_eq!(
+            take_arg(&mut values, "--input").unwrap().as_deref(),
+            Some("a.csv")
+        );
+        assert_eq!(
+            take_arg(&mut values, "--phase_plane").unwrap().as_deref(),
+            Some("b.csv")
+        );
+        assert_eq!(take_arg(&mut values, "--resume").unwrap(), None);
+        assert_eq!(values, args(&["--seed", "3"]));
+
+        let err = take_arg(&mut args(&["--input"]), "--input").unwrap_err();
+        assert_eq!(err, "Missing value for --input");
+    }
+
+    fn rejected(values: &[&str]) -> String {
+        WsgParams::default().with_args(args(values)).unwrap_err()
+    }
+
+    #[test]
+    fn zero_dimensions_are_rejected() {
+        assert!(rejected(&["--dim", "0,0"]).starts_with("Invalid value for --dim"));
+        assert!(rejected(&["--dim", "10,0"]).starts_with("Invalid value for --dim"));
+    }
+
+    #[test]
+    fn probabilities_out_of_range_are_rejected() {
+        assert!(rejected(&["--sheep_repr", "1.5"]).starts_with("Invalid value for --sheep_repr"));
+        assert!(rejected(&["--wolf_repr", "-0.1"]).starts_with("Invalid value for --wolf_repr"));
+        assert!(rejected(&["--momentum_probability", "2"])
+            .starts_with("Invalid value for --momentum_probability"));
+    }
+
+    #[test]
+    fn negative_energies_are_rejected() {
+        assert!(
+            rejected(&["--energy_consume", "-1"]).starts_with("Invalid value for --energy_consume")
+        );
+        assert!(rejected(&["--gain_energy_sheep", "-0.5"])
+            .starts_with("Invalid value for --gain_energy_sheep"));
+        assert!(rejected(&["--gain_energy_wolf", "-2"])
+            .starts_with("Invalid value for --gain_energy_wolf"));
+    }
+
+    #[test]
+    fn zero_perception_radius_is_rejected_with_directed_movement() {
+        let err = rejected(&["--movement", "directed", "--perception_radius", "0"]);
+        assert!(err.starts_with("Invalid value for --perception_radius"));
+        // The radius is not used by the random walk
+        assert!(WsgParams::default()
+            .with_args(args(&["--perception_radius", "0"]))
+            .is_ok());
+    }
+}
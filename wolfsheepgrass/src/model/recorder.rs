@@ -0,0 +1,266 @@
+use crate::model::params::WsgParams;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+// Population dynamics of a single step, one row of the output file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub step: u64,
+    pub sheep: u64,
+    pub wolves: u64,
+    pub born_sheep: u64,
+    pub born_wolves: u64,
+    pub killed_sheep: u64,
+    pub starved_sheep: u64,
+    pub dead_wolves: u64,
+    // Sum of the grass values of all the cells
+    pub grass_biomass: u64,
+    pub mean_energy_sheep: f64,
+    pub mean_energy_wolves: f64,
+}
+
+// Writes one StepRecord per step, for headless runs. The format depends on the extension of the
+// output file: `.parquet` (requires the `parquet` feature) or CSV for anything else.
+// The parameters of the run, seed included, are stored in a `<output>.params.toml` sidecar file.
+pub struct Recorder {
+    sink: Sink,
+}
+
+enum Sink {
+    Csv(Box<csv::Writer<File>>),
+    // Parquet rows are written in row groups of ROW_GROUP_ROWS steps, the file is only readable
+    // once finished
+    #[cfg(feature = "parquet")]
+    Parquet(
+        PathBuf,
+        Option<Box<parquet_output::Writer>>,
+        Vec<StepRecord>,
+    ),
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P, params: &WsgParams) -> Result<Recorder, String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+        }
+        let sidecar = sidecar_path(path);
+        let content = toml::to_string(params).map_err(|e| format!("Invalid parameters: {}", e))?;
+        fs::write(&sidecar, content)
+            .map_err(|e| format!("Unable to write {}: {}", sidecar.display(), e))?;
+
+        let sink = if is_parquet(path) {
+            #[cfg(feature = "parquet")]
+            {
+                let writer = parquet_output::Writer::create(path)
+                    .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+                Sink::Parquet(path.to_path_buf(), Some(Box::new(writer)), Vec::new())
+            }
+            #[cfg(not(feature = "parquet"))]
+            return Err(String::from(
+                "Parquet output requires the `parquet` feature",
+            ));
+        } else {
+            let writer = csv::Writer::from_path(path)
+                .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+            Sink::Csv(Box::new(writer))
+        };
+
+        Ok(Recorder { sink })
+    }
+
+    // Continue the output of a run resumed at `step`. The rows of a CSV file up to that step are
+    // kept and the following ones, written before the run was interrupted, are dropped.
+    // Parquet files are only written at the end of a run, so they start over.
+    pub fn resume<P: AsRef<Path>>(
+        path: P,
+        params: &WsgParams,
+        step: u64,
+    ) -> Result<Recorder, String> {
+        let path = path.as_ref();
+        let mut kept = Vec::new();
+        if path.exists() && !is_parquet(path) {
+            let mut reader = csv::Reader::from_path(path)
+                .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+            for record in reader.deserialize::<StepRecord>() {
+                let record =
+                    record.map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+                if record.step < step {
+                    kept.push(record);
+                }
+            }
+        }
+
+        let mut recorder = Recorder::create(path, params)?;
+        for record in kept.iter() {
+            recorder.record(record)?;
+        }
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, record: &StepRecord) -> Result<(), String> {
+        match &mut self.sink {
+            // Flush every step: a crashed run keeps all the rows written so far
+            Sink::Csv(writer) => writer
+                .serialize(record)
+                .and_then(|_| writer.flush().map_err(csv::Error::from))
+                .map_err(|e| format!("Unable to write the step record: {}", e)),
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(path, writer, records) => {
+                records.push(*record);
+                if records.len() < parquet_output::ROW_GROUP_ROWS {
+                    return Ok(());
+                }
+                let writer = writer
+                    .as_mut()
+                    .ok_or_else(|| format!("{} is already finished", path.display()))?;
+                writer
+                    .write_row_group(records)
+                    .map_err(|e| format!("Unable to write {}: {}", path.display(), e))?;
+                records.clear();
+                Ok(())
+            }
+        }
+    }
+
+    // Write the rows still pending and close the file, after the last step of the run.
+    pub fn finish(&mut self) -> Result<(), String> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer
+                .flush()
+                .map_err(|e| format!("Unable to write the step record: {}", e)),
+            #[cfg(feature = "parquet")]
+            Sink::Parquet(path, writer, records) => match writer.take() {
+                Some(mut writer) => writer
+                    .write_row_group(records)
+                    .and_then(|_| writer.close())
+                    .map(|_| records.clear())
+                    .map_err(|e| format!("Unable to write {}: {}", path.display(), e)),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+// A run that stops before its last step still gets a readable Parquet file.
+#[cfg(feature = "parquet")]
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            eprintln!("{}", err);
+        }
+    }
+}
+
+fn is_parquet(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("parquet"))
+}
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".params.toml");
+    path.with_file_name(name)
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_output {
+    use super::StepRecord;
+    use parquet::data_type::{DoubleType, Int64Type};
+    use parquet::errors::Result;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::fs::File;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    // Same columns, in the same order, of StepRecord
+    const SCHEMA: &str = "
+        message wsg_step {
+            REQUIRED INT64 step;
+            REQUIRED INT64 sheep;
+            REQUIRED INT64 wolves;
+            REQUIRED INT64 born_sheep;
+            REQUIRED INT64 born_wolves;
+            REQUIRED INT64 killed_sheep;
+            REQUIRED INT64 starved_sheep;
+            REQUIRED INT64 dead_wolves;
+            REQUIRED INT64 grass_biomass;
+            REQUIRED DOUBLE mean_energy_sheep;
+            REQUIRED DOUBLE mean_energy_wolves;
+        }
+    ";
+
+    fn int_column(record: &StepRecord, column: usize) -> i64 {
+        let value = match column {
+            0 => record.step,
+            1 => record.sheep,
+            2 => record.wolves,
+            3 => record.born_sheep,
+            4 => record.born_wolves,
+            5 => record.killed_sheep,
+            6 => record.starved_sheep,
+            7 => record.dead_wolves,
+            _ => record.grass_biomass,
+        };
+        value as i64
+    }
+
+    // Steps written in each row group
+    pub const ROW_GROUP_ROWS: usize = 1024;
+
+    pub struct Writer {
+        writer: SerializedFileWriter<File>,
+    }
+
+    impl Writer {
+        pub fn create(path: &Path) -> Result<Writer> {
+            let schema = Arc::new(parse_message_type(SCHEMA)?);
+            let props = Arc::new(WriterProperties::builder().build());
+            let writer = SerializedFileWriter::new(File::create(path)?, schema, props)?;
+            Ok(Writer { writer })
+        }
+
+        pub fn write_row_group(&mut self, records: &[StepRecord]) -> Result<()> {
+            if records.is_empty() {
+                return Ok(());
+            }
+            let mut row_group = self.writer.next_row_group()?;
+            let mut column = 0;
+            while let Some(mut column_writer) = row_group.next_column()? {
+                if column < 9 {
+                    let values: Vec<i64> = records.iter().map(|r| int_column(r, column)).collect();
+                    column_writer
+                        .typed::<Int64Type>()
+                        .write_batch(&values, None, None)?;
+                } else {
+                    let values: Vec<f64> = records
+                        .iter()
+                        .map(|r| {
+                            if column == 9 {
+                                r.mean_energy_sheep
+                            } else {
+                                r.mean_energy_wolves
+                            }
+                        })
+                        .collect();
+                    column_writer
+                        .typed::<DoubleType>()
+                        .write_batch(&values, None, None)?;
+                }
+                column_writer.close()?;
+                column += 1;
+            }
+            row_group.close()?;
+            Ok(())
+        }
+
+        pub fn close(self) -> Result<()> {
+            self.writer.close()?;
+            Ok(())
+        }
+    }
+}
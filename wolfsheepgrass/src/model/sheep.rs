@@ -1,23 +1,29 @@
+use crate::model::checkpoint::{int2d, option_int2d};
+use crate::model::events::Grazing;
 use crate::model::state::{LifeState, WsgState};
-use crate::{ENERGY_CONSUME, FULL_GROWN, GAIN_ENERGY_SHEEP, MOMENTUM_PROBABILITY, SHEEP_REPR};
+use crate::model::traits::moves_per_step;
 
 use core::fmt;
 use krabmaga::engine::agent::Agent;
 use krabmaga::engine::location::Int2D;
 use krabmaga::engine::state::State;
-use krabmaga::rand;
 use krabmaga::rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Sheep {
     pub id: u32,
     pub animal_state: LifeState,
+    #[serde(with = "int2d")]
     pub loc: Int2D,
+    #[serde(with = "option_int2d")]
     pub last: Option<Int2D>,
     pub energy: f64,
     pub gain_energy: f64,
     pub prob_reproduction: f64,
+    // Cells travelled in a step, the fractional part is the probability of an extra cell
+    pub speed: f64,
 }
 
 impl Sheep {
@@ -27,6 +33,7 @@ impl Sheep {
         energy: f64,
         gain_energy: f64,
         prob_reproduction: f64,
+        speed: f64,
     ) -> Sheep {
         Sheep {
             id,
@@ -35,10 +42,16 @@ impl Sheep {
             energy,
             gain_energy,
             prob_reproduction,
+            speed,
             animal_state: LifeState::Alive,
         }
     }
 
+    // Heritable traits, in the order of TRAIT_NAMES
+    pub fn traits(&self) -> [f64; 3] {
+        [self.gain_energy, self.prob_reproduction, self.speed]
+    }
+
     #[allow(dead_code)]
     pub fn as_agent(self) -> Box<dyn Agent> {
         Box::new(self)
@@ -47,81 +60,64 @@ impl Sheep {
 
 impl Agent for Sheep {
     fn step(&mut self, state: &mut dyn State) {
-        let state = state.as_any_mut().downcast_mut::<WsgState>().unwrap();
-        // CHECK IF I AM DEAD
-        if self.animal_state == LifeState::Dead {
+        let state = state.as_any().downcast_ref::<WsgState>().unwrap();
+        // CHECK IF I AM DEAD, OR IF A WOLF HAS EATEN ME DURING THE LAST STEP
+        if self.animal_state == LifeState::Dead || state.killed_sheep.contains(self) {
             return;
         }
-        //MOVE
-        let x = self.loc.x;
-        let y = self.loc.y;
-        let mut rng = rand::rng();
-
-        let mut moved = false;
-        if self.last.is_some() && rng.random_bool(MOMENTUM_PROBABILITY) {
-            if let Some(last_loc) = self.last {
-                let xm = x + (x - last_loc.x);
-                let ym = y + (y - last_loc.y);
-                let new_loc = Int2D { x: xm, y: ym };
-                // TRY TO MOVE WITH MOMENTUM_PROBABILITY
-                if xm >= 0 && xm < state.dim.0 && ym >= 0 && ym < state.dim.1 {
-                    self.loc = new_loc;
-                    self.last = Some(Int2D { x, y });
-                    moved = true;
-                }
-            }
+
+        //DIGEST THE GRASS EATEN DURING THE LAST STEP
+        if let Some(energy) = state.meals.get(&self.id) {
+            self.energy += energy;
+        }
+        if self.energy <= 0.0 {
+            self.animal_state = LifeState::Dead;
+            state.events.lock().unwrap().starved_sheep.push(*self);
+            return;
         }
 
-        if !moved {
-            let xmin = if x > 0 { -1 } else { 0 };
-            let xmax = i32::from(x < state.dim.0 - 1);
-            let ymin = if y > 0 { -1 } else { 0 };
-            let ymax = i32::from(y < state.dim.1 - 1);
-
-            // let nx = if rng.random_bool(0.5) { xmin } else { xmax };
-            // let ny = if rng.random_bool(0.5) { ymin } else { ymax };
-            let nx = rng.random_range(xmin..=xmax);
-            let ny = rng.random_range(ymin..=ymax);
-
-            self.loc = Int2D {
-                x: x + nx,
-                y: y + ny,
-            };
-            self.last = Some(Int2D { x, y });
+        //MOVE
+        let mut rng = state.agent_rng(self.id);
+        for _ in 0..moves_per_step(self.speed, &mut rng) {
+            let new_loc = state.sheep_move(&self.loc, self.last, &mut rng);
+            self.last = Some(self.loc);
+            self.loc = new_loc;
         }
 
         state.sheep_grid.set_object_location(*self, &self.loc);
-        //EAT
-        if state.grass_field.get_value_unbuffered(&self.loc).is_none() {
-            if let Some(grass_val) = state.grass_field.get_value(&self.loc) {
-                if grass_val >= FULL_GROWN {
-                    state.grass_field.set_value_location(0, &self.loc);
-                    self.energy += self.gain_energy;
-                }
+        //EAT: other sheep may want the same cell, the lowest id gets it in after_step
+        if let Some(grass_val) = state.grass_field.get_value(&self.loc) {
+            if grass_val > 0 && grass_val >= state.grass_capacity(&self.loc) {
+                state.events.lock().unwrap().grazings.push(Grazing {
+                    sheep: self.id,
+                    loc: self.loc,
+                    gain_energy: self.gain_energy,
+                });
             }
         }
 
         //UPDATE ENERGY
-        self.energy -= ENERGY_CONSUME;
-        if self.energy <= 0.0 {
-            self.animal_state = LifeState::Dead;
-        } else {
-            //REPRODUCE
-            if rng.random_bool(self.prob_reproduction) {
-                self.energy /= 2.0;
-                //let mut new_id = state.next_id;
-
-                let new_sheep = Sheep::new(
-                    state.next_id,
-                    self.loc,
-                    self.energy,
-                    GAIN_ENERGY_SHEEP,
-                    SHEEP_REPR,
-                );
-
-                state.next_id += 1;
-                state.new_sheep.push(new_sheep);
-            }
+        self.energy -= state.energy_cost(self.speed);
+        //REPRODUCE
+        if self.energy > 0.0 && rng.random_bool(self.prob_reproduction) {
+            self.energy /= 2.0;
+
+            let [gain_energy, prob_reproduction, speed] =
+                state.params.mutation.inherit(self.traits(), &mut rng);
+            let new_sheep = Sheep::new(
+                0,
+                self.loc,
+                self.energy,
+                gain_energy,
+                prob_reproduction,
+                speed,
+            );
+            state
+                .events
+                .lock()
+                .unwrap()
+                .sheep_births
+                .push((self.id, new_sheep));
         }
     }
 
@@ -5,18 +5,28 @@ use krabmaga::engine::{
 
 use krabmaga::*;
 
+use super::checkpoint::Checkpoint;
+use super::events::StepEvents;
+use super::params::WsgParams;
+use super::recorder::{Recorder, StepRecord};
 use super::sheep::Sheep;
+use super::terrain::Terrain;
+use super::traits::{TraitStats, TRAIT_NAMES};
 use super::wolf::Wolf;
-use crate::{FULL_GROWN, GAIN_ENERGY_SHEEP, GAIN_ENERGY_WOLF, SHEEP_REPR, WOLF_REPR};
 use core::fmt;
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
+use krabmaga::engine::agent::Agent;
+#[cfg(feature = "parallel")]
+use krabmaga::engine::agentimpl::AgentImpl;
 use krabmaga::engine::fields::grid_option::GridOption;
-use krabmaga::rand;
-use krabmaga::rand::Rng;
+use krabmaga::rand::rngs::StdRng;
+use krabmaga::rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::any::Any;
+use std::sync::{Arc, Mutex};
 // pub use std::time::{Duration,Instant};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum LifeState {
     Alive,
     Dead,
@@ -40,25 +50,243 @@ pub struct WsgState {
     pub next_id: u32,
     pub new_sheep: Vec<Sheep>,
     pub new_wolves: Vec<Wolf>,
+    // Sheep eaten during the last step. They are still in the read buffer of sheep_grid during the
+    // following step, wolves must ignore them.
     pub killed_sheep: HashSet<Sheep>,
-    pub initial_animals: (u32, u32),
+    // Animals that ran out of energy during the current step
+    pub starved_sheep: Vec<Sheep>,
+    pub dead_wolves: Vec<Wolf>,
+    // Events reported by the agents during the current step, resolved in after_step
+    pub events: Mutex<StepEvents>,
+    // Energy gained by each animal that ate during the last step, by id
+    pub meals: HashMap<u32, f64>,
+    pub params: WsgParams,
+    pub terrain: Option<Terrain>,
+    // Writes the population dynamics of each step when an output file is set in the parameters
+    pub recorder: Option<Recorder>,
+    // Error of the recorder that stopped the run, shared with main to report it once the
+    // simulation is over
+    pub error: Arc<Mutex<Option<String>>>,
+    // Population dynamics of every executed step, used by the analysis tools
+    pub history: Vec<StepRecord>,
+    // Checkpoint restored by init instead of generating a new population
+    pub resume: Option<Checkpoint>,
 }
 
 impl WsgState {
-    pub fn new(dim: (i32, i32), initial_animals: (u32, u32)) -> WsgState {
+    // The random number generator of an agent for the current step. Each stream is derived from the
+    // master seed, the step and the agent id, so it does not depend on the order in which the
+    // schedule executes the agents.
+    pub fn agent_rng(&self, id: u32) -> StdRng {
+        StdRng::seed_from_u64(mix(mix(self.params.seed, self.step), id as u64))
+    }
+
+    pub fn new(params: WsgParams) -> WsgState {
+        let dim = params.dim;
         WsgState {
             dim,
             wolves_grid: DenseGrid2D::new(dim.0, dim.1),
             sheep_grid: DenseGrid2D::new(dim.0, dim.1),
             grass_field: DenseNumberGrid2D::new(dim.0, dim.1),
             step: 0,
-            next_id: initial_animals.1 + initial_animals.0,
+            next_id: params.initial_wolves + params.initial_sheep,
             new_sheep: Vec::new(),
             new_wolves: Vec::new(),
-            initial_animals,
             killed_sheep: HashSet::new(),
+            starved_sheep: Vec::new(),
+            dead_wolves: Vec::new(),
+            events: Mutex::new(StepEvents::default()),
+            meals: HashMap::new(),
+            params,
+            terrain: None,
+            recorder: None,
+            error: Arc::new(Mutex::new(None)),
+            history: Vec::new(),
+            resume: None,
+        }
+    }
+
+    // A state that continues the run saved in the checkpoint, with the given parameters.
+    pub fn resume(params: WsgParams, checkpoint: Checkpoint) -> WsgState {
+        let mut state = WsgState::new(params);
+        state.resume = Some(checkpoint);
+        state
+    }
+
+    // Restore the field, the schedule and the bookkeeping of the run saved in the checkpoint.
+    fn restore(&mut self, checkpoint: Checkpoint, schedule: &mut Schedule) {
+        for (i, grass) in checkpoint.grass.iter().enumerate() {
+            let loc = Int2D {
+                x: i as i32 / self.dim.1,
+                y: i as i32 % self.dim.1,
+            };
+            self.grass_field.set_value_location(*grass, &loc);
+        }
+        for sheep in checkpoint.sheep_grid {
+            self.sheep_grid.set_object_location(sheep, &sheep.loc);
+        }
+        for wolf in checkpoint.wolves_grid {
+            self.wolves_grid.set_object_location(wolf, &wolf.loc);
+        }
+        self.grass_field.lazy_update();
+        self.sheep_grid.lazy_update();
+        self.wolves_grid.lazy_update();
+
+        // Same orderings used by generate_sheep and generate_wolves. Animals are dequeued by their
+        // id, so each one is scheduled under it instead of the next id of the schedule.
+        for sheep in checkpoint.sheep {
+            set_next_agent_id(schedule, sheep.id);
+            schedule.schedule_repeating(Box::new(sheep), checkpoint.time + 1.0, 0);
+        }
+        for wolf in checkpoint.wolves {
+            set_next_agent_id(schedule, wolf.id);
+            schedule.schedule_repeating(Box::new(wolf), checkpoint.time + 1.0, 1);
+        }
+        set_next_agent_id(schedule, checkpoint.next_id);
+        set_schedule_step(schedule, checkpoint.step);
+        schedule.time = checkpoint.time;
+
+        self.step = checkpoint.step;
+        self.next_id = checkpoint.next_id;
+        self.killed_sheep = checkpoint.killed_sheep.into_iter().collect();
+        self.meals = checkpoint.meals.into_iter().collect();
+    }
+
+    // Load the terrain layer set in the parameters, if any. It is loaded before the run starts, so
+    // that a missing or invalid raster is reported like any other invalid parameter.
+    pub fn load_terrain(&mut self) -> Result<(), String> {
+        self.terrain = match &self.params.terrain {
+            Some(terrain) => Some(Terrain::load(terrain, self.dim)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    // Whether animals can walk on a cell. Always true without a terrain layer.
+    pub fn is_passable(&self, loc: &Int2D) -> bool {
+        match &self.terrain {
+            Some(terrain) => terrain.class(loc).passable,
+            None => true,
         }
     }
+
+    // Maximum value of the grass on a cell: sheep only eat cells that reached it.
+    pub fn grass_capacity(&self, loc: &Int2D) -> u16 {
+        match &self.terrain {
+            Some(terrain) => {
+                let class = terrain.class(loc);
+                if class.passable {
+                    class.capacity
+                } else {
+                    0
+                }
+            }
+            None => self.params.full_grown,
+        }
+    }
+
+    // Energy consumed in a step by an animal with the given speed. Half of energy_consume is the
+    // basal metabolism, the other half grows with the speed, so that speed 1 costs energy_consume.
+    pub fn energy_cost(&self, speed: f64) -> f64 {
+        self.params.energy_consume * (1. + speed) / 2.
+    }
+
+    // A random location that can be occupied by an animal.
+    fn random_location(&self, rng: &mut StdRng) -> Int2D {
+        loop {
+            let loc = Int2D {
+                x: rng.random_range(0..self.dim.0),
+                y: rng.random_range(0..self.dim.1),
+            };
+            if self.is_passable(&loc) {
+                return loc;
+            }
+        }
+    }
+
+    // Collect the population dynamics of the step that has just been executed, with the
+    // distribution of the heritable traits of the living sheep and wolves. Both come from the same
+    // scan of the schedule, which is the costly part on large populations.
+    pub fn step_record(&self, schedule: &Schedule) -> (StepRecord, TraitStats, TraitStats) {
+        let mut record = StepRecord {
+            step: schedule_step(schedule),
+            born_sheep: self.new_sheep.len() as u64,
+            born_wolves: self.new_wolves.len() as u64,
+            killed_sheep: self.killed_sheep.len() as u64,
+            starved_sheep: self.starved_sheep.len() as u64,
+            dead_wolves: self.dead_wolves.len() as u64,
+            ..StepRecord::default()
+        };
+
+        // Energies are summed in id order: the order of the schedule changes when a run is
+        // resumed from a checkpoint, and a floating point sum depends on it
+        let mut sheep_energy = Vec::new();
+        let mut wolves_energy = Vec::new();
+        let mut sheep_stats = TraitStats::default();
+        let mut wolves_stats = TraitStats::default();
+        for n in schedule.get_all_events() {
+            if let Some(s) = n.downcast_ref::<Sheep>() {
+                sheep_energy.push((s.id, s.energy));
+                sheep_stats.add(s.traits());
+            }
+            if let Some(w) = n.downcast_ref::<Wolf>() {
+                wolves_energy.push((w.id, w.energy));
+                wolves_stats.add(w.traits());
+            }
+        }
+        record.sheep = sheep_energy.len() as u64;
+        record.wolves = wolves_energy.len() as u64;
+        if record.sheep > 0 {
+            record.mean_energy_sheep = sum_by_id(&mut sheep_energy) / record.sheep as f64;
+        }
+        if record.wolves > 0 {
+            record.mean_energy_wolves = sum_by_id(&mut wolves_energy) / record.wolves as f64;
+        }
+
+        // Scanning the whole field is expensive on large grids, only do it when it is recorded
+        if self.recorder.is_some() {
+            record.grass_biomass = self.grass_biomass();
+        }
+        (record, sheep_stats, wolves_stats)
+    }
+
+    // Grass growth driven by the terrain layer, each cell has its own regrowth rate and capacity.
+    fn grow_grass_on_terrain(&self) {
+        for x in 0..self.dim.0 {
+            for y in 0..self.dim.1 {
+                let loc = Int2D { x, y };
+                // Cells eaten during this step start growing back from the next one
+                if self.grass_field.get_value_unbuffered(&loc).is_some() {
+                    continue;
+                }
+                let grass = self.grass_field.get_value(&loc).unwrap_or(0);
+                let capacity = self.grass_capacity(&loc);
+                let regrowth = match &self.terrain {
+                    Some(terrain) => terrain.class(&loc).regrowth,
+                    None => 1,
+                };
+                let growth = grass.saturating_add(regrowth).min(capacity);
+                self.grass_field.set_value_location(growth, &loc);
+            }
+        }
+    }
+
+    // Total amount of grass on the field, including the cells eaten during the current step.
+    pub fn grass_biomass(&self) -> u64 {
+        let mut biomass = 0;
+        for x in 0..self.dim.0 {
+            for y in 0..self.dim.1 {
+                let loc = Int2D { x, y };
+                let grass = self
+                    .grass_field
+                    .get_value_unbuffered(&loc)
+                    .or_else(|| self.grass_field.get_value(&loc))
+                    .unwrap_or(0);
+                biomass += grass as u64;
+            }
+        }
+        biomass
+    }
 }
 
 impl State for WsgState {
@@ -67,22 +295,42 @@ impl State for WsgState {
         self.wolves_grid = DenseGrid2D::new(self.dim.0, self.dim.1);
         self.sheep_grid = DenseGrid2D::new(self.dim.0, self.dim.1);
         self.grass_field = DenseNumberGrid2D::new(self.dim.0, self.dim.1);
-        self.next_id = self.initial_animals.0 + self.initial_animals.1;
+        self.next_id = self.params.initial_sheep + self.params.initial_wolves;
         self.new_sheep = Vec::new();
         self.new_wolves = Vec::new();
-        self.initial_animals = (self.initial_animals.0, self.initial_animals.1);
+        self.killed_sheep = HashSet::new();
+        self.starved_sheep = Vec::new();
+        self.dead_wolves = Vec::new();
+        self.events = Mutex::new(StepEvents::default());
+        self.meals = HashMap::new();
+        self.history = Vec::new();
     }
 
     fn init(&mut self, schedule: &mut Schedule) {
         self.reset();
+        if let Some(output) = &self.params.output {
+            let recorder = match &self.resume {
+                Some(checkpoint) => Recorder::resume(output, &self.params, checkpoint.step),
+                None => Recorder::create(output, &self.params),
+            };
+            match recorder {
+                Ok(recorder) => self.recorder = Some(recorder),
+                Err(err) => *self.error.lock().unwrap() = Some(err),
+            }
+        }
         let s = "Also known as Wolf Sheep predation, it is the simulation implemented to introduce \"dynamic scheduling\"
                         feature into the krabmaga framework, because it was the first model with the concepts of \"death\" and \"birth\":
                         there is an ecosystem that involves animals into their life-cycle.".to_string();
         description!(s);
 
-        generate_grass(self);
-        generate_wolves(self, schedule);
-        generate_sheep(self, schedule);
+        if let Some(checkpoint) = self.resume.take() {
+            self.restore(checkpoint, schedule);
+        } else {
+            let mut rng = StdRng::seed_from_u64(self.params.seed);
+            generate_grass(self, &mut rng);
+            generate_wolves(self, schedule, &mut rng);
+            generate_sheep(self, schedule, &mut rng);
+        }
 
         addplot!(
             String::from("Agents"),
@@ -97,14 +345,26 @@ impl State for WsgState {
             String::from("Number of agents"),
             true
         );
+
+        for plot_name in ["Sheep traits", "Wolf traits"] {
+            addplot!(
+                String::from(plot_name),
+                String::from("Steps"),
+                String::from("Trait value"),
+                true
+            );
+        }
     }
 
     fn update(&mut self, step: u64) {
-        if step != 0 {
+        if step != 0 && self.terrain.is_some() {
+            self.grow_grass_on_terrain();
+        } else if step != 0 {
+            let full_grown = self.params.full_grown;
             self.grass_field.apply_to_all_values(
                 |grass| {
                     let growth = *grass;
-                    if growth < FULL_GROWN {
+                    if growth < full_grown {
                         growth + 1
                     } else {
                         growth
@@ -137,84 +397,235 @@ impl State for WsgState {
         self
     }
 
-    fn before_step(&mut self, _schedule: &mut Schedule) {
+    // The run stops when its output cannot be written
+    fn end_condition(&mut self, _schedule: &mut Schedule) -> bool {
+        self.error.lock().unwrap().is_some()
+    }
+
+    fn before_step(&mut self, schedule: &mut Schedule) {
+        let every = self.params.checkpoint_every;
+        let step = schedule_step(schedule);
+        if every > 0 && step > 0 && step.is_multiple_of(every) {
+            // Like the recorder, a checkpoint that cannot be written stops the run
+            if let Err(err) = Checkpoint::new(self, schedule).save(&self.params.checkpoint_dir) {
+                *self.error.lock().unwrap() = Some(err);
+            }
+        }
+
         self.new_sheep.clear();
         self.new_wolves.clear();
+        self.starved_sheep.clear();
+        self.dead_wolves.clear();
     }
 
     fn after_step(&mut self, schedule: &mut Schedule) {
-        for sheep in self.new_sheep.iter() {
-            schedule.schedule_repeating(Box::new(*sheep), schedule.time + 1.0, 0);
+        let mut events = std::mem::take(self.events.get_mut().unwrap());
+
+        // A contested sheep is eaten by the wolf with the lowest id, the others stay hungry
+        self.meals.clear();
+        self.killed_sheep.clear();
+        events
+            .predations
+            .sort_unstable_by_key(|predation| (predation.sheep.id, predation.wolf));
+        events
+            .predations
+            .dedup_by_key(|predation| predation.sheep.id);
+        for predation in events.predations {
+            self.meals.insert(predation.wolf, predation.gain_energy);
+            self.killed_sheep.insert(predation.sheep);
+            dequeue(schedule, Box::new(predation.sheep), predation.sheep.id);
         }
 
-        for wolf in self.new_wolves.iter() {
-            schedule.schedule_repeating(Box::new(*wolf), schedule.time + 1.0, 1);
+        // A sheep eaten during this step neither grazes nor gives birth
+        let killed: HashSet<u32> = self.killed_sheep.iter().map(|sheep| sheep.id).collect();
+        events
+            .grazings
+            .retain(|grazing| !killed.contains(&grazing.sheep));
+        events
+            .sheep_births
+            .retain(|(parent, _)| !killed.contains(parent));
+
+        // Offspring get their ids in the order of the ids of their parents
+        events
+            .sheep_births
+            .sort_unstable_by_key(|(parent, _)| *parent);
+        for (_, mut sheep) in events.sheep_births {
+            sheep.id = self.next_id;
+            self.next_id += 1;
+            schedule.schedule_repeating(Box::new(sheep), schedule.time + 1.0, 0);
+            self.new_sheep.push(sheep);
         }
 
-        for sheep in self.killed_sheep.iter() {
-            schedule.dequeue(Box::new(*sheep), sheep.id);
+        events
+            .wolf_births
+            .sort_unstable_by_key(|(parent, _)| *parent);
+        for (_, mut wolf) in events.wolf_births {
+            wolf.id = self.next_id;
+            self.next_id += 1;
+            schedule.schedule_repeating(Box::new(wolf), schedule.time + 1.0, 1);
+            self.new_wolves.push(wolf);
         }
 
-        let agents = schedule.get_all_events();
-        let mut num_sheep: f32 = 0.;
-        let mut num_wolves: f32 = 0.;
+        // Sheep grazing on the same cell: the one with the lowest id eats the grass
+        events
+            .grazings
+            .sort_unstable_by_key(|grazing| (grazing.loc.x, grazing.loc.y, grazing.sheep));
+        events.grazings.dedup_by_key(|grazing| grazing.loc);
+        for grazing in events.grazings {
+            self.grass_field.set_value_location(0, &grazing.loc);
+            self.meals.insert(grazing.sheep, grazing.gain_energy);
+        }
 
-        for n in agents {
-            if let Some(_s) = n.downcast_ref::<Sheep>() {
-                num_sheep += 1.;
-            }
-            if let Some(_w) = n.downcast_ref::<Wolf>() {
-                num_wolves += 1.;
-            }
+        // Starved animals did not set their location during this step, so they are already gone
+        // from the grids. A sheep can starve and be claimed by a wolf in the same step: the wolf
+        // has already dequeued it.
+        events.starved_sheep.sort_unstable_by_key(|sheep| sheep.id);
+        let killed = &self.killed_sheep;
+        events.starved_sheep.retain(|sheep| !killed.contains(sheep));
+        for sheep in events.starved_sheep.iter() {
+            dequeue(schedule, Box::new(*sheep), sheep.id);
         }
+        self.starved_sheep = events.starved_sheep;
+
+        events.dead_wolves.sort_unstable_by_key(|wolf| wolf.id);
+        for wolf in events.dead_wolves.iter() {
+            dequeue(schedule, Box::new(*wolf), wolf.id);
+        }
+        self.dead_wolves = events.dead_wolves;
+
+        let (record, sheep_stats, wolves_stats) = self.step_record(schedule);
 
         plot!(
             String::from("Agents"),
             String::from("Wolfs"),
             schedule.step as f64,
-            num_wolves as f64
+            record.wolves as f64
         );
         plot!(
             String::from("Agents"),
             String::from("Sheep"),
             schedule.step as f64,
-            num_sheep as f64
+            record.sheep as f64
         );
 
         plot!(
             String::from("Dead/Born"),
             String::from("Dead Sheep"),
             schedule.step as f64,
-            self.killed_sheep.len() as f64
+            record.killed_sheep as f64
+        );
+        plot!(
+            String::from("Dead/Born"),
+            String::from("Starved Sheep"),
+            schedule.step as f64,
+            record.starved_sheep as f64
+        );
+        plot!(
+            String::from("Dead/Born"),
+            String::from("Dead Wolves"),
+            schedule.step as f64,
+            record.dead_wolves as f64
         );
         plot!(
             String::from("Dead/Born"),
             String::from("Born Wolfs"),
             schedule.step as f64,
-            self.new_wolves.len() as f64
+            record.born_wolves as f64
         );
         plot!(
             String::from("Dead/Born"),
             String::from("Born Sheep"),
             schedule.step as f64,
-            self.new_sheep.len() as f64
+            record.born_sheep as f64
         );
 
-        self.killed_sheep.clear();
+        for (plot_name, stats) in [("Sheep traits", sheep_stats), ("Wolf traits", wolves_stats)] {
+            for (i, trait_name) in TRAIT_NAMES.iter().enumerate() {
+                plot!(
+                    String::from(plot_name),
+                    format!("{} (mean)", trait_name),
+                    schedule.step as f64,
+                    stats.mean(i)
+                );
+                plot!(
+                    String::from(plot_name),
+                    format!("{} (variance)", trait_name),
+                    schedule.step as f64,
+                    stats.variance(i)
+                );
+            }
+        }
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            let mut written = recorder.record(&record);
+            if written.is_ok() && schedule_step(schedule) + 1 >= self.params.step {
+                written = recorder.finish();
+            }
+            if let Err(err) = written {
+                *self.error.lock().unwrap() = Some(err);
+                self.recorder = None;
+            }
+        }
+        self.history.push(record);
     }
 }
 
-fn generate_grass(state: &mut WsgState) {
-    (0..state.dim.1).into_iter().for_each(|x| {
-        (0..state.dim.0).into_iter().for_each(|y| {
-            let mut rng = rand::rng();
+// The parallel schedule has no dequeue, and counts its steps and the ids of its agents with other
+// types. These helpers hide the differences from the model.
+#[cfg(not(feature = "parallel"))]
+fn dequeue(schedule: &mut Schedule, agent: Box<dyn Agent>, id: u32) {
+    schedule.dequeue(agent, id);
+}
+
+#[cfg(feature = "parallel")]
+fn dequeue(schedule: &mut Schedule, agent: Box<dyn Agent>, id: u32) {
+    schedule
+        .events
+        .lock()
+        .unwrap()
+        .remove(&AgentImpl::new(agent, id));
+}
+
+// Id given to the next agent added to the schedule.
+#[cfg(not(feature = "parallel"))]
+fn set_next_agent_id(schedule: &mut Schedule, id: u32) {
+    schedule.agent_ids_counting = id;
+}
+
+#[cfg(feature = "parallel")]
+fn set_next_agent_id(schedule: &mut Schedule, id: u32) {
+    *schedule.agent_ids_counting.lock().unwrap() = id;
+}
+
+#[allow(clippy::unnecessary_cast)]
+pub fn schedule_step(schedule: &Schedule) -> u64 {
+    schedule.step as u64
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn set_schedule_step(schedule: &mut Schedule, step: u64) {
+    schedule.step = step as _;
+}
+
+// SplitMix64 finalizer, used to derive independent seeds from the master one.
+fn mix(seed: u64, value: u64) -> u64 {
+    let mut z = seed ^ value.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+fn generate_grass(state: &mut WsgState, rng: &mut StdRng) {
+    (0..state.dim.0).for_each(|x| {
+        (0..state.dim.1).for_each(|y| {
+            let full_grown = state.grass_capacity(&Int2D { x, y });
             let fully_growth = rng.random_bool(0.5);
             if fully_growth {
                 state
                     .grass_field
-                    .set_value_location(FULL_GROWN, &Int2D { x, y });
+                    .set_value_location(full_grown, &Int2D { x, y });
             } else {
-                let grass_init_value = rng.random_range(0..FULL_GROWN + 1);
+                let grass_init_value = rng.random_range(0..full_grown + 1);
                 state
                     .grass_field
                     .set_value_location(grass_init_value, &Int2D { x, y });
@@ -223,21 +634,22 @@ fn generate_grass(state: &mut WsgState) {
     });
 }
 
-fn generate_sheep(state: &mut WsgState, schedule: &mut Schedule) {
-    let mut rng = rand::rng();
+fn sum_by_id(energies: &mut [(u32, f64)]) -> f64 {
+    energies.sort_unstable_by_key(|(id, _)| *id);
+    energies.iter().map(|(_, energy)| energy).sum()
+}
 
-    for id in 0..state.initial_animals.0 {
-        let loc = Int2D {
-            x: rng.random_range(0..state.dim.0),
-            y: rng.random_range(0..state.dim.1),
-        };
-        let init_energy = rng.random_range(0..(2 * GAIN_ENERGY_SHEEP as usize));
+fn generate_sheep(state: &mut WsgState, schedule: &mut Schedule, rng: &mut StdRng) {
+    for id in 0..state.params.initial_sheep {
+        let loc = state.random_location(rng);
+        let init_energy = rng.random_range(0. ..=2. * state.params.gain_energy_sheep);
         let sheep = Sheep::new(
-            id + state.initial_animals.1,
+            id + state.params.initial_wolves,
             loc,
-            init_energy as f64,
-            GAIN_ENERGY_SHEEP,
-            SHEEP_REPR,
+            init_energy,
+            state.params.gain_energy_sheep,
+            state.params.sheep_repr,
+            state.params.speed_sheep,
         );
         state.sheep_grid.set_object_location(sheep, &loc);
 
@@ -245,20 +657,199 @@ fn generate_sheep(state: &mut WsgState, schedule: &mut Schedule) {
     }
 }
 
-fn generate_wolves(state: &mut WsgState, schedule: &mut Schedule) {
-    let mut rng = rand::rng();
-    for id in 0..state.initial_animals.1 {
-        let loc = Int2D {
-            x: rng.random_range(0..state.dim.0),
-            y: rng.random_range(0..state.dim.1),
-        };
-        let init_energy = rng.random_range(0..(2 * GAIN_ENERGY_WOLF as usize));
+fn generate_wolves(state: &mut WsgState, schedule: &mut Schedule, rng: &mut StdRng) {
+    for id in 0..state.params.initial_wolves {
+        let loc = state.random_location(rng);
+        let init_energy = rng.random_range(0. ..=2. * state.params.gain_energy_wolf);
 
-        let wolf = Wolf::new(id, loc, init_energy as f64, GAIN_ENERGY_WOLF, WOLF_REPR);
+        let wolf = Wolf::new(
+            id,
+            loc,
+            init_energy,
+            state.params.gain_energy_wolf,
+            state.params.wolf_repr,
+            state.params.speed_wolf,
+        );
         state.wolves_grid.set_object_location(wolf, &loc);
 
-        // Sheep have an higher ordering than wolves. This is so that if a wolf kills one, in the next step
-        // the attacked sheep will immediately notice and die, instead of noticing after two steps.
+        // Predation is resolved in after_step, so the ordering of sheep and wolves does not
+        // change the outcome of a step.
         schedule.schedule_repeating(Box::new(wolf), 0., 1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::terrain::{TerrainClass, TerrainParams};
+
+    // Population counts (sheep, wolves) after each of the first `steps` steps of a small run.
+    fn populations(seed: u64, steps: u64) -> Vec<(usize, usize)> {
+        let params = WsgParams {
+            dim: (30, 30),
+            initial_sheep: 60,
+            initial_wolves: 40,
+            seed,
+            ..WsgParams::default()
+        };
+        let mut state = WsgState::new(params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+
+        (0..steps)
+            .map(|_| {
+                schedule.step(&mut state);
+                let agents = schedule.get_all_events();
+                let sheep = agents
+                    .iter()
+                    .filter(|a| a.downcast_ref::<Sheep>().is_some());
+                let wolves = agents.iter().filter(|a| a.downcast_ref::<Wolf>().is_some());
+                (sheep.count(), wolves.count())
+            })
+            .collect()
+    }
+
+    fn scheduled_sheep(schedule: &Schedule) -> HashSet<u32> {
+        schedule
+            .get_all_events()
+            .iter()
+            .filter_map(|agent| agent.downcast_ref::<Sheep>().map(|sheep| sheep.id))
+            .collect()
+    }
+
+    fn sheep_on_grid(state: &WsgState) -> HashSet<u32> {
+        let ids = std::cell::RefCell::new(HashSet::new());
+        state.sheep_grid.iter_objects(|_, sheep| {
+            ids.borrow_mut().insert(sheep.id);
+        });
+        ids.into_inner()
+    }
+
+    #[test]
+    fn dead_sheep_leave_the_grid_and_the_schedule() {
+        // Many wolves and little grass energy, so that sheep are both eaten and starved
+        let params = WsgParams {
+            dim: (20, 20),
+            initial_sheep: 200,
+            initial_wolves: 100,
+            gain_energy_sheep: 2.,
+            seed: 42,
+            ..WsgParams::default()
+        };
+        let mut state = WsgState::new(params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+
+        let (mut killed, mut starved) = (0, 0);
+        let mut killed_before: HashSet<u32> = HashSet::new();
+        for _ in 0..30 {
+            schedule.step(&mut state);
+            let scheduled = scheduled_sheep(&schedule);
+            let on_grid = sheep_on_grid(&state);
+            for sheep in state.killed_sheep.iter().chain(state.starved_sheep.iter()) {
+                assert!(!scheduled.contains(&sheep.id));
+            }
+            // A starved sheep did not move during the step, an eaten one is on the grid until
+            // the end of the following step
+            for sheep in state.starved_sheep.iter() {
+                assert!(!on_grid.contains(&sheep.id));
+            }
+            for id in killed_before.iter() {
+                assert!(!on_grid.contains(id));
+            }
+            killed += state.killed_sheep.len();
+            starved += state.starved_sheep.len();
+            killed_before = state.killed_sheep.iter().map(|sheep| sheep.id).collect();
+        }
+        assert!(killed > 0 && starved > 0);
+    }
+
+    // Animals start with up to twice their gain of energy, which may be below 1
+    #[test]
+    fn small_energy_gains_are_initialized() {
+        let params = WsgParams {
+            dim: (10, 10),
+            initial_sheep: 20,
+            initial_wolves: 10,
+            gain_energy_sheep: 0.4,
+            gain_energy_wolf: 0.,
+            ..WsgParams::default()
+        };
+        let mut state = WsgState::new(params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        state.sheep_grid.iter_objects(|_, sheep| {
+            assert!((0. ..=0.8).contains(&sheep.energy));
+        });
+        state.wolves_grid.iter_objects(|_, wolf| {
+            assert_eq!(wolf.energy, 0.);
+        });
+    }
+
+    #[test]
+    fn unwritable_output_stops_the_run() {
+        let params = WsgParams {
+            dim: (10, 10),
+            initial_sheep: 10,
+            initial_wolves: 5,
+            output: Some(String::from("/dev/null/wsg.csv")),
+            ..WsgParams::default()
+        };
+        let mut state = WsgState::new(params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        schedule.step(&mut state);
+        assert!(state.end_condition(&mut schedule));
+        let error = state.error.lock().unwrap().clone().unwrap();
+        assert!(error.starts_with("Unable to create /dev/null"));
+    }
+
+    #[test]
+    fn unwritable_checkpoint_stops_the_run() {
+        let params = WsgParams {
+            dim: (10, 10),
+            initial_sheep: 10,
+            initial_wolves: 5,
+            checkpoint_every: 1,
+            checkpoint_dir: String::from("/dev/null/checkpoints"),
+            ..WsgParams::default()
+        };
+        let mut state = WsgState::new(params);
+        let mut schedule = Schedule::new();
+        state.init(&mut schedule);
+        schedule.step(&mut state);
+        assert!(!state.end_condition(&mut schedule));
+        schedule.step(&mut state);
+        assert!(state.end_condition(&mut schedule));
+        let error = state.error.lock().unwrap().clone().unwrap();
+        assert!(error.starts_with("Unable to create /dev/null/checkpoints"));
+    }
+
+    #[test]
+    fn missing_terrain_raster_is_an_error() {
+        let params = WsgParams {
+            dim: (10, 10),
+            terrain: Some(TerrainParams {
+                raster: String::from("/nonexistent/terrain.csv"),
+                classes: vec![TerrainClass::default()],
+            }),
+            ..WsgParams::default()
+        };
+        let mut state = WsgState::new(params);
+        let err = state.load_terrain().unwrap_err();
+        assert!(err.starts_with("Unable to read /nonexistent/terrain.csv"));
+        assert!(state.terrain.is_none());
+    }
+
+    #[test]
+    fn same_seed_reproduces_populations() {
+        let first = populations(42, 50);
+        let second = populations(42, 50);
+        assert_eq!(first, second);
+        assert_ne!(first, populations(43, 50));
+        // Golden values: a change of these counts is a change of the dynamics of the model, or of
+        // the way the random streams are derived from the seed
+        assert_eq!(first[9], (90, 53));
+        assert_eq!(first[49], (53, 120));
+    }
+}
@@ -0,0 +1,102 @@
+use krabmaga::engine::location::Int2D;
+use serde::{Deserialize, Serialize};
+
+// A kind of terrain: how fast grass grows back on its cells, the maximum value it can reach
+// (sheep only eat fully grown cells) and whether animals can walk on it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerrainClass {
+    pub regrowth: u16,
+    pub capacity: u16,
+    pub passable: bool,
+}
+
+impl Default for TerrainClass {
+    fn default() -> Self {
+        TerrainClass {
+            regrowth: 1,
+            capacity: 20,
+            passable: true,
+        }
+    }
+}
+
+// Terrain section of the parameters. The raster is a CSV file without header, with one row for
+// each y coordinate and one column for each x coordinate; each value is the index of a class.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TerrainParams {
+    pub raster: String,
+    pub classes: Vec<TerrainClass>,
+}
+
+// Static layer describing the class of every cell of the field.
+pub struct Terrain {
+    pub height: i32,
+    pub classes: Vec<TerrainClass>,
+    cells: Vec<u8>,
+}
+
+impl Terrain {
+    pub fn load(params: &TerrainParams, dim: (i32, i32)) -> Result<Terrain, String> {
+        if params.classes.is_empty() || params.classes.len() > u8::MAX as usize + 1 {
+            return Err(String::from("The terrain needs between 1 and 256 classes"));
+        }
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .trim(csv::Trim::All)
+            .from_path(&params.raster)
+            .map_err(|e| format!("Unable to read {}: {}", params.raster, e))?;
+
+        let mut rows: Vec<Vec<u8>> = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| format!("Unable to read {}: {}", params.raster, e))?;
+            let row = record
+                .iter()
+                .map(|value| match value.parse::<u8>() {
+                    Ok(class) if (class as usize) < params.classes.len() => Ok(class),
+                    _ => Err(format!(
+                        "Invalid terrain class {} in {}",
+                        value, params.raster
+                    )),
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+            rows.push(row);
+        }
+
+        if rows.len() != dim.1 as usize || rows.iter().any(|row| row.len() != dim.0 as usize) {
+            return Err(format!(
+                "The terrain raster {} must be {}x{} cells",
+                params.raster, dim.0, dim.1
+            ));
+        }
+
+        let mut cells = vec![0; (dim.0 * dim.1) as usize];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, class) in row.iter().enumerate() {
+                cells[x * dim.1 as usize + y] = *class;
+            }
+        }
+
+        if !cells
+            .iter()
+            .any(|class| params.classes[*class as usize].passable)
+        {
+            return Err(format!(
+                "The terrain raster {} has no passable cell",
+                params.raster
+            ));
+        }
+
+        Ok(Terrain {
+            height: dim.1,
+            classes: params.classes.clone(),
+            cells,
+        })
+    }
+
+    pub fn class(&self, loc: &Int2D) -> &TerrainClass {
+        &self.classes[self.cells[(loc.x * self.height + loc.y) as usize] as usize]
+    }
+}
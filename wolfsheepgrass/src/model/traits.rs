@@ -0,0 +1,78 @@
+use krabmaga::rand::rngs::StdRng;
+use krabmaga::rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Heritable traits of an animal, in the order used by TraitStats.
+pub const TRAIT_NAMES: [&str; 3] = ["Gain energy", "Reproduction", "Speed"];
+
+// Standard deviations of the Gaussian noise added to each trait of an offspring.
+// With the defaults (all zero) offspring are exact copies of their parent.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutationParams {
+    pub gain_energy: f64,
+    pub prob_reproduction: f64,
+    pub speed: f64,
+}
+
+impl MutationParams {
+    // Traits of an offspring: the ones of the parent plus Gaussian noise, kept in their valid range.
+    pub fn inherit(&self, traits: [f64; 3], rng: &mut StdRng) -> [f64; 3] {
+        [
+            mutate(traits[0], self.gain_energy, rng).max(0.),
+            mutate(traits[1], self.prob_reproduction, rng).clamp(0., 1.),
+            mutate(traits[2], self.speed, rng).max(0.),
+        ]
+    }
+}
+
+fn mutate(value: f64, sd: f64, rng: &mut StdRng) -> f64 {
+    if sd <= 0. {
+        return value;
+    }
+    // Box-Muller transform, 1 - u keeps the logarithm finite
+    let u: f64 = rng.random();
+    let v: f64 = rng.random();
+    let normal = (-2. * (1. - u).ln()).sqrt() * (2. * std::f64::consts::PI * v).cos();
+    value + sd * normal
+}
+
+// Mean and variance of the traits of a population.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TraitStats {
+    count: u64,
+    sum: [f64; 3],
+    sum_sq: [f64; 3],
+}
+
+impl TraitStats {
+    pub fn add(&mut self, traits: [f64; 3]) {
+        self.count += 1;
+        for (i, value) in traits.iter().enumerate() {
+            self.sum[i] += value;
+            self.sum_sq[i] += value * value;
+        }
+    }
+
+    pub fn mean(&self, i: usize) -> f64 {
+        if self.count == 0 {
+            return 0.;
+        }
+        self.sum[i] / self.count as f64
+    }
+
+    pub fn variance(&self, i: usize) -> f64 {
+        if self.count == 0 {
+            return 0.;
+        }
+        let mean = self.mean(i);
+        (self.sum_sq[i] / self.count as f64 - mean * mean).max(0.)
+    }
+}
+
+// Cells travelled in a step by an animal with the given speed: the integer part of the speed,
+// plus one more cell with a probability equal to its fractional part.
+pub fn moves_per_step(speed: f64, rng: &mut StdRng) -> u32 {
+    let extra = speed.fract() > 0. && rng.random_bool(speed.fract());
+    speed.trunc() as u32 + u32::from(extra)
+}
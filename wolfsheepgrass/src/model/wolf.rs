@@ -1,27 +1,40 @@
 use core::fmt;
 use krabmaga::{
     engine::{agent::Agent, location::Int2D, state::State},
-    rand,
     rand::Rng,
 };
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 
+use crate::model::checkpoint::{int2d, option_int2d};
+use crate::model::events::Predation;
 use crate::model::state::{LifeState, WsgState};
-use crate::{ENERGY_CONSUME, GAIN_ENERGY_WOLF, MOMENTUM_PROBABILITY, WOLF_REPR};
+use crate::model::traits::moves_per_step;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Wolf {
     pub id: u32,
     pub animal_state: LifeState,
+    #[serde(with = "int2d")]
     pub loc: Int2D,
+    #[serde(with = "option_int2d")]
     pub last: Option<Int2D>,
     pub energy: f64,
     pub gain_energy: f64,
     pub prob_reproduction: f64,
+    // Cells travelled in a step, the fractional part is the probability of an extra cell
+    pub speed: f64,
 }
 
 impl Wolf {
-    pub fn new(id: u32, loc: Int2D, energy: f64, gain_energy: f64, prob_reproduction: f64) -> Wolf {
+    pub fn new(
+        id: u32,
+        loc: Int2D,
+        energy: f64,
+        gain_energy: f64,
+        prob_reproduction: f64,
+        speed: f64,
+    ) -> Wolf {
         Wolf {
             id,
             loc,
@@ -29,10 +42,16 @@ impl Wolf {
             energy,
             gain_energy,
             prob_reproduction,
+            speed,
             animal_state: LifeState::Alive,
         }
     }
 
+    // Heritable traits, in the order of TRAIT_NAMES
+    pub fn traits(&self) -> [f64; 3] {
+        [self.gain_energy, self.prob_reproduction, self.speed]
+    }
+
     #[allow(dead_code)]
     pub fn as_agent(self) -> Box<dyn Agent> {
         Box::new(self)
@@ -41,82 +60,70 @@ impl Wolf {
 
 impl Agent for Wolf {
     fn step(&mut self, state: &mut dyn State) {
-        let state = state.as_any_mut().downcast_mut::<WsgState>().unwrap();
-        let x = self.loc.x;
-        let y = self.loc.y;
-        let mut rng = rand::rng();
-
+        let state = state.as_any().downcast_ref::<WsgState>().unwrap();
         // CHECK IF I AM DEAD
         if self.animal_state == LifeState::Dead {
             return;
         }
 
-        let mut moved = false;
-        if self.last.is_some() && rng.random_bool(MOMENTUM_PROBABILITY) {
-            if let Some(last_loc) = self.last {
-                let xm = x + (x - last_loc.x);
-                let ym = y + (y - last_loc.y);
-                let new_loc = Int2D { x: xm, y: ym };
-                // TRY TO MOVE WITH MOMENTUM_PROBABILITY
-                if xm >= 0 && xm < state.dim.0 && ym >= 0 && ym < state.dim.1 {
-                    self.loc = new_loc;
-                    self.last = Some(Int2D { x, y });
-                    moved = true;
-                }
-            }
+        //DIGEST THE SHEEP EATEN DURING THE LAST STEP
+        if let Some(energy) = state.meals.get(&self.id) {
+            self.energy += energy;
+        }
+        if self.energy <= 0.0 {
+            self.animal_state = LifeState::Dead;
+            state.events.lock().unwrap().dead_wolves.push(*self);
+            return;
         }
-        if !moved {
-            let xmin = if x > 0 { -1 } else { 0 };
-            let xmax = i32::from(x < state.dim.0 - 1);
-            let ymin = if y > 0 { -1 } else { 0 };
-            let ymax = i32::from(y < state.dim.1 - 1);
-
-            let nx = rng.random_range(xmin..=xmax);
-            let ny = rng.random_range(ymin..=ymax);
-            self.loc = Int2D {
-                x: x + nx,
-                y: y + ny,
-            };
-            self.last = Some(Int2D { x, y });
+
+        let mut rng = state.agent_rng(self.id);
+        for _ in 0..moves_per_step(self.speed, &mut rng) {
+            let new_loc = state.wolf_move(&self.loc, self.last, &mut rng);
+            self.last = Some(self.loc);
+            self.loc = new_loc;
         }
 
         state.wolves_grid.set_object_location(*self, &self.loc);
 
-        //EAT
+        //EAT: claim the sheep with the lowest id, the lowest wolf id gets it in after_step
         if let Some(sheep) = state.sheep_grid.get_objects(&self.loc) {
-            for mut sheep in sheep {
-                if state.killed_sheep.get(&sheep).is_none()
-                    && sheep.animal_state == LifeState::Alive
-                {
-                    sheep.animal_state = LifeState::Dead;
-                    state.sheep_grid.remove_object_location(sheep, &sheep.loc);
-                    self.energy += self.gain_energy;
-                    state.killed_sheep.insert(sheep);
-                    break;
-                }
+            let prey = sheep
+                .into_iter()
+                .filter(|sheep| {
+                    sheep.animal_state == LifeState::Alive && !state.killed_sheep.contains(sheep)
+                })
+                .min_by_key(|sheep| sheep.id);
+            if let Some(sheep) = prey {
+                state.events.lock().unwrap().predations.push(Predation {
+                    wolf: self.id,
+                    sheep,
+                    gain_energy: self.gain_energy,
+                });
             }
         }
 
         //UPDATE ENERGY
-        self.energy -= ENERGY_CONSUME;
-        if self.energy <= 0.0 {
-            self.animal_state = LifeState::Dead;
-        } else {
-            //REPRODUCE
-            if rng.random_bool(self.prob_reproduction) {
-                self.energy /= 2.0;
-
-                let new_wolf = Wolf::new(
-                    state.next_id,
-                    self.loc,
-                    self.energy,
-                    GAIN_ENERGY_WOLF,
-                    WOLF_REPR,
-                );
-
-                state.next_id += 1;
-                state.new_wolves.push(new_wolf);
-            }
+        self.energy -= state.energy_cost(self.speed);
+        //REPRODUCE
+        if self.energy > 0.0 && rng.random_bool(self.prob_reproduction) {
+            self.energy /= 2.0;
+
+            let [gain_energy, prob_reproduction, speed] =
+                state.params.mutation.inherit(self.traits(), &mut rng);
+            let new_wolf = Wolf::new(
+                0,
+                self.loc,
+                self.energy,
+                gain_energy,
+                prob_reproduction,
+                speed,
+            );
+            state
+                .events
+                .lock()
+                .unwrap()
+                .wolf_births
+                .push((self.id, new_wolf));
         }
     }
 
@@ -1,25 +1,55 @@
 use crate::model::state::WsgState;
-use crate::FULL_GROWN;
-use krabmaga::engine::fields::dense_number_grid_2d::DenseNumberGrid2D;
 use krabmaga::engine::location::Int2D;
 use krabmaga::{bevy::prelude::Image, visualization::fields::number_grid_2d::BatchRender};
 
-impl BatchRender<WsgState> for DenseNumberGrid2D<u16> {
+// Growth of the grass of every cell, as a fraction of the capacity of the cell. The pixel callback
+// only sees the rendered struct, so the growth is computed from the state on every frame.
+pub struct GrassGrowth {
+    width: i32,
+    height: i32,
+    // None for the cells where grass never grows
+    growth: Vec<Option<f32>>,
+}
+
+impl GrassGrowth {
+    pub fn new(state: &WsgState) -> GrassGrowth {
+        let (width, height) = state.dim;
+        let mut growth = Vec::with_capacity((width * height) as usize);
+        for x in 0..width {
+            for y in 0..height {
+                let loc = Int2D { x, y };
+                let capacity = state.grass_capacity(&loc);
+                growth.push(if capacity == 0 {
+                    None
+                } else {
+                    let grass = state.grass_field.get_value(&loc).unwrap_or(0);
+                    Some(grass as f32 / capacity as f32)
+                });
+            }
+        }
+        GrassGrowth {
+            width,
+            height,
+            growth,
+        }
+    }
+}
+
+impl BatchRender<WsgState> for GrassGrowth {
     fn get_pixel(&self, loc: &Int2D) -> [u8; 4] {
-        match self.get_value(loc) {
-            Some(val) => {
-                let growth = val;
-                if (growth as f32 / FULL_GROWN as f32) < 0.5 {
+        match self.growth[(loc.x * self.height + loc.y) as usize] {
+            Some(growth) => {
+                if growth < 0.5 {
                     [139u8, 69u8, 19u8, 180u8]
-                } else if (growth as f32 / FULL_GROWN as f32) < 0.7 {
+                } else if growth < 0.7 {
                     [128u8, 128u8, 0u8, 150u8]
-                } else if growth == FULL_GROWN {
+                } else if growth >= 1. {
                     [0u8, 128u8, 0u8, 255u8]
                 } else {
                     [0u8, 255u8, 0u8, 255u8]
                 }
             }
-            None => [0u8, 255u8, 0u8, 0u8],
+            None => [90u8, 90u8, 90u8, 255u8],
         }
     }
 
@@ -32,6 +62,6 @@ impl BatchRender<WsgState> for DenseNumberGrid2D<u16> {
     }
 
     fn get_texture_from_state(state: &WsgState) -> Image {
-        state.grass_field.texture()
+        GrassGrowth::new(state).texture()
     }
 }
@@ -1,6 +1,7 @@
 use crate::model::sheep::Sheep;
 use crate::model::state::WsgState;
 use crate::model::wolf::Wolf;
+use crate::visualization::grass_vis::GrassGrowth;
 use crate::visualization::sheep_vis::SheepVis;
 use crate::visualization::wolf_vis::WolfVis;
 use krabmaga::bevy::ecs as bevy_ecs;
@@ -57,6 +58,7 @@ impl VisualizationState<WsgState> for VisState {
                 0.,
                 0.,
                 0.,
+                0.,
             )) {
                 Some(matching_agent) => Some(Box::new(matching_agent)),
                 None => None,
@@ -68,6 +70,7 @@ impl VisualizationState<WsgState> for VisState {
                 0.,
                 0.,
                 0.,
+                0.,
             )) {
                 Some(matching_agent) => Some(Box::new(matching_agent)),
                 None => None,
@@ -127,8 +130,6 @@ impl VisState {
         commands: &mut Commands,
         sim: &mut SimulationDescriptor,
     ) {
-        state
-            .grass_field
-            .render(&mut *sprite_render_factory, commands, sim);
+        GrassGrowth::new(state).render(&mut *sprite_render_factory, commands, sim);
     }
 }